@@ -0,0 +1,216 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    error::{ErrorType, RuntimeError},
+    expr::{self, Literal},
+    interpreter::Interpreter,
+    list,
+    stmt::{Flow, Function},
+};
+
+pub type NativeFnPtr = fn(&mut Interpreter, Vec<Literal>) -> Result<Literal, RuntimeError>;
+
+#[derive(Debug, Clone)]
+pub struct NativeFn {
+    name: &'static str,
+    arity: usize,
+    function: NativeFnPtr,
+}
+
+impl NativeFn {
+    pub fn new(name: &'static str, arity: usize, function: NativeFnPtr) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Callable {
+    Native(NativeFn),
+    // a built-in method together with the value it was looked up on, e.g. `xs.push`
+    Method(Box<Literal>, NativeFn),
+    Function(Rc<Closure>),
+}
+
+// a function declared in the script, with the scope it was declared in
+pub struct Closure {
+    declaration: Rc<Function>,
+    closure: Rc<RefCell<Environment>>,
+    script: Rc<str>, // the file it was declared in
+}
+
+impl Closure {
+    pub fn new(
+        declaration: Rc<Function>,
+        closure: Rc<RefCell<Environment>>,
+        script: Rc<str>,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            script,
+        }
+    }
+}
+
+// environments can hold the function itself, so only the name is shown
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Closure({})", self.declaration.name())
+    }
+}
+
+impl Callable {
+    pub fn name(&self) -> &str {
+        match self {
+            Callable::Native(native) | Callable::Method(_, native) => native.name,
+            Callable::Function(function) => function.declaration.name(),
+        }
+    }
+
+    // the fewest and most arguments accepted, no most for functions with a rest parameter
+    fn arity(&self) -> (usize, Option<usize>) {
+        match self {
+            Callable::Native(native) | Callable::Method(_, native) => {
+                (native.arity, Some(native.arity))
+            }
+            Callable::Function(function) => (
+                function.declaration.min_arity(),
+                function.declaration.max_arity(),
+            ),
+        }
+    }
+
+    // `line` is where the call is made from, for stack traces
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        args: Vec<Literal>,
+        line: u32,
+    ) -> Result<Literal, RuntimeError> {
+        self.check_arity(&args)?;
+
+        let script = match self {
+            Callable::Function(function) => Some(function.script.clone()),
+            Callable::Native(_) | Callable::Method(..) => None,
+        };
+        interpreter.push_frame(self.name(), line, script)?;
+        let result = self
+            .invoke(interpreter, args)
+            .map_err(|err| interpreter.traced(err));
+        interpreter.pop_frame();
+        result
+    }
+
+    fn invoke(
+        &self,
+        interpreter: &mut Interpreter,
+        args: Vec<Literal>,
+    ) -> Result<Literal, RuntimeError> {
+        match self {
+            Callable::Native(native) => (native.function)(interpreter, args),
+            Callable::Function(function) => {
+                let scope = Environment::with_enclosing(function.closure.clone());
+                interpreter.with_environment(Rc::new(RefCell::new(scope)), |interpreter| {
+                    function.bind_args(interpreter, args)?;
+                    match interpreter.execute_stmts(&function.declaration.body)? {
+                        Flow::Return(val) => Ok(val),
+                        _ => Ok(Literal::Nil),
+                    }
+                })
+            }
+            Callable::Method(receiver, native) => {
+                // the receiver is passed as the first argument, it doesn't count towards the arity
+                let mut with_receiver = Vec::with_capacity(args.len() + 1);
+                with_receiver.push(receiver.as_ref().clone());
+                with_receiver.extend(args);
+                (native.function)(interpreter, with_receiver)
+            }
+        }
+    }
+}
+
+impl Callable {
+    // natives are looked up by name, so the name identifies them
+    pub fn same_as(&self, other: &Callable) -> bool {
+        match (self, other) {
+            (Callable::Native(l), Callable::Native(r)) => l.name == r.name,
+            (Callable::Method(l_receiver, l), Callable::Method(r_receiver, r)) => {
+                l.name == r.name && expr::values_equal(l_receiver, r_receiver)
+            }
+            (Callable::Function(l), Callable::Function(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+
+    fn check_arity(&self, args: &[Literal]) -> Result<(), RuntimeError> {
+        let (min, max) = self.arity();
+        if args.len() >= min && max.is_none_or(|max| args.len() <= max) {
+            return Ok(());
+        }
+
+        let expected = match max {
+            Some(max) if max == min => arguments(min),
+            Some(max) => format!("{} to {}", min, arguments(max)),
+            None => format!("at least {}", arguments(min)),
+        };
+        Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!(
+                "{} expected {} but got {}",
+                self.name(),
+                expected,
+                args.len()
+            ),
+        ))
+    }
+}
+
+fn arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_owned(),
+        count => format!("{} arguments", count),
+    }
+}
+
+impl Closure {
+    // defines the parameters in the function's scope, which is the current one by now, so
+    // defaults can use the parameters before them
+    fn bind_args(
+        &self,
+        interpreter: &mut Interpreter,
+        args: Vec<Literal>,
+    ) -> Result<(), RuntimeError> {
+        let mut args = args.into_iter();
+        for param in &self.declaration.params {
+            let val = match (args.next(), &param.default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => default.interpret_ast(interpreter)?,
+                (None, None) => unreachable!("arity is checked before binding"),
+            };
+            interpreter.define(param.name.lexeme.to_string(), val)?;
+        }
+        if let Some(rest) = &self.declaration.rest {
+            interpreter.define(rest.lexeme.to_string(), list::new_list(args.collect()))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Callable::Native(native) => write!(f, "<native fn {}>", native.name),
+            Callable::Method(_, native) => write!(f, "<native method {}>", native.name),
+            Callable::Function(function) => write!(f, "<fn {}>", function.declaration.name()),
+        }
+    }
+}
@@ -1,10 +1,18 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
-use crate::expr::Literal;
+use crate::{
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+};
 
 pub struct Environment {
     enclosing: Option<Rc<RefCell<Environment>>>, // reference to parent environment, for scoping
     map: HashMap<String, Literal>,
+    constants: HashSet<String>, // names in map that were declared with `const`
 }
 
 impl Environment {
@@ -12,6 +20,7 @@ impl Environment {
         Self {
             enclosing: None,
             map: HashMap::new(),
+            constants: HashSet::new(),
         }
     }
 
@@ -19,28 +28,82 @@ impl Environment {
         Self {
             enclosing: Some(parent),
             map: HashMap::new(),
+            constants: HashSet::new(),
         }
     }
 
     pub fn get(&self, key: &str) -> Option<Literal> {
         self.map
             .get(key)
-            .map(|lit| lit.clone())
+            .cloned()
             .or_else(|| self.enclosing.as_ref()?.borrow().get(key))
     }
 
-    pub fn define(&mut self, key: String, val: Literal) {
+    // ignores enclosing scopes
+    pub fn get_own(&self, key: &str) -> Option<Literal> {
+        self.map.get(key).cloned()
+    }
+
+    // a constant can't be redeclared in its own scope, only shadowed from an inner one
+    pub fn define(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        self.check_redeclarable(&key)?;
         self.map.insert(key, val);
+        Ok(())
     }
 
-    pub fn assign(&mut self, key: String, val: Literal) -> Result<(), String> {
-        if self.map.contains_key(&key) {
-            self.map.insert(key, val);
+    pub fn define_const(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        self.check_redeclarable(&key)?;
+        self.constants.insert(key.clone());
+        self.map.insert(key, val);
+        Ok(())
+    }
+
+    fn check_redeclarable(&self, key: &str) -> Result<(), RuntimeError> {
+        match self.constants.contains(key) {
+            true => Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("Cannot redeclare constant: {}", key),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    pub fn assign(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        if self.constants.contains(&key) {
+            Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("Cannot assign to constant: {}", key),
+            ))
+        } else if let Some(slot) = self.map.get_mut(&key) {
+            *slot = val;
             Ok(())
         } else if let Some(env) = self.enclosing.as_ref() {
             env.borrow_mut().assign(key, val)
         } else {
-            Err(format!("Undefined variable: {}", key))
+            Err(RuntimeError::new(
+                ErrorType::NameError,
+                format!("Undefined variable: {}", key),
+            ))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_cannot_be_redeclared_in_its_own_scope() {
+        let mut globals = Environment::new();
+        globals
+            .define_const("c".to_owned(), Literal::Integer(1))
+            .unwrap();
+        assert!(globals.define("c".to_owned(), Literal::Integer(5)).is_err());
+        assert!(globals
+            .define_const("c".to_owned(), Literal::Integer(5))
+            .is_err());
+
+        let mut inner = Environment::with_enclosing(Rc::new(RefCell::new(globals)));
+        assert!(inner.define("c".to_owned(), Literal::Integer(5)).is_ok());
+    }
+}
@@ -0,0 +1,173 @@
+use std::{fmt, rc::Rc};
+
+use crate::expr::Literal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorType {
+    Error, // anything not given a more specific type, e.g. a native function failing
+    TypeError,
+    NameError,
+    IndexError,
+    ValueError, // the right type but a value the operation can't take, e.g. an empty separator
+    ArithmeticError,
+    StackOverflowError,
+    ImportError,
+    IOError,
+}
+
+impl fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorType::Error => write!(f, "Error"),
+            ErrorType::TypeError => write!(f, "TypeError"),
+            ErrorType::NameError => write!(f, "NameError"),
+            ErrorType::IndexError => write!(f, "IndexError"),
+            ErrorType::ValueError => write!(f, "ValueError"),
+            ErrorType::ArithmeticError => write!(f, "ArithmeticError"),
+            ErrorType::StackOverflowError => write!(f, "StackOverflowError"),
+            ErrorType::ImportError => write!(f, "ImportError"),
+            ErrorType::IOError => write!(f, "IOError"),
+        }
+    }
+}
+
+// what a script's `catch` gets for an error the interpreter raised
+#[derive(Debug)]
+pub struct ErrorObject {
+    error_type: ErrorType,
+    message: String,
+    line: Option<u32>,
+}
+
+impl ErrorObject {
+    pub fn property(&self, name: &str) -> Option<Literal> {
+        match name {
+            "message" => Some(Literal::String(self.message.clone())),
+            "type" => Some(Literal::String(self.error_type.to_string())),
+            "line" => Some(
+                self.line
+                    .map_or(Literal::Nil, |line| Literal::Integer(line as i64)),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.error_type, self.message)
+    }
+}
+
+#[derive(Debug)]
+enum Thrown {
+    Error(ErrorType, String), // raised by the interpreter
+    Value(Literal),           // thrown by the script, any value can be
+    Exit(i32),                // from exit(), no `catch` stops it
+}
+
+// unwinds statements until a `try` catches it or it reaches the top level
+#[derive(Debug)]
+pub struct RuntimeError {
+    thrown: Thrown,
+    line: Option<u32>,
+    trace: Option<Vec<String>>, // innermost call first, filled in by the first call it leaves
+}
+
+// traces longer than this only show the innermost calls
+const MAX_TRACE_LINES: usize = 20;
+
+impl RuntimeError {
+    pub fn new(error_type: ErrorType, message: String) -> Self {
+        Self {
+            thrown: Thrown::Error(error_type, message),
+            line: None,
+            trace: None,
+        }
+    }
+
+    pub fn throw(value: Literal, line: u32) -> Self {
+        Self {
+            thrown: Thrown::Value(value),
+            line: Some(line),
+            trace: None,
+        }
+    }
+
+    pub fn exit(code: i32) -> Self {
+        Self {
+            thrown: Thrown::Exit(code),
+            line: None,
+            trace: None,
+        }
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.thrown {
+            Thrown::Exit(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    // only the innermost line is kept, so errors point at where they happened
+    pub fn at(mut self, line: u32) -> Self {
+        self.line.get_or_insert(line);
+        self
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    pub fn has_trace(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    pub fn with_trace(mut self, trace: Vec<String>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    // the value a `catch` binds
+    pub fn into_value(self) -> Literal {
+        match self.thrown {
+            Thrown::Error(error_type, message) => Literal::Error(Rc::new(ErrorObject {
+                error_type,
+                message,
+                line: self.line,
+            })),
+            Thrown::Value(value) => value,
+            Thrown::Exit(_) => unreachable!("exits are never caught"),
+        }
+    }
+}
+
+// errors from natives and collection helpers don't say what kind they are
+impl From<String> for RuntimeError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorType::Error, message)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "[Line {}] ", line)?;
+        }
+        match &self.thrown {
+            Thrown::Error(error_type, message) => write!(f, "{}: {}", error_type, message),
+            Thrown::Value(Literal::Error(error)) => write!(f, "{}", error),
+            Thrown::Value(value) => write!(f, "Uncaught exception: {}", value),
+            Thrown::Exit(code) => write!(f, "Exited with code {}", code),
+        }?;
+
+        let trace = self.trace.as_deref().unwrap_or_default();
+        for frame in trace.iter().take(MAX_TRACE_LINES) {
+            write!(f, "\n    at {}", frame)?;
+        }
+        if trace.len() > MAX_TRACE_LINES {
+            write!(f, "\n    ... {} more", trace.len() - MAX_TRACE_LINES)?;
+        }
+        Ok(())
+    }
+}
@@ -1,17 +1,37 @@
-use std::{cell::RefCell, fmt, rc::Rc};
+use std::{cmp::Ordering, collections::HashSet, fmt, rc::Rc};
 
 use crate::{
-    environment::Environment,
+    callable::{Callable, Closure},
+    error::{ErrorObject, ErrorType, RuntimeError},
+    interpreter::{DivisionByZero, Interpreter, Options},
+    list::{self, List},
+    map::{self, Key, Map, OrderedMap},
+    module::Module,
+    natives,
+    stmt::Function,
+    string,
     tokens::{self, Token},
 };
 
 pub enum Expr {
     Literal(Literal),
+    List(ListLiteral),
+    Map(MapLiteral),
     Unary(Unary),
     Binary(Binary),
+    Logical(Logical),
+    Comparison(Comparison),
+    Conditional(Conditional),
+    Sequence(Vec<Expr>),
     Grouping(Grouping),
-    Variable(Token, Rc<RefCell<Environment>>),
-    Assign(Assign, Rc<RefCell<Environment>>),
+    Variable(Token),
+    Assign(Assign),
+    Call(Call),
+    Get(Get),
+    Index(Index),
+    IndexAssign(IndexAssign),
+    Update(Update),
+    Function(Rc<Function>),
 }
 
 impl Expr {
@@ -90,125 +110,643 @@ impl Expr {
     //     }
     // }
 
-    pub fn interpret_ast(self) -> Result<Literal, String> {
+    pub fn interpret_ast(&self, interpreter: &mut Interpreter) -> Result<Literal, RuntimeError> {
+        interpreter.check_stack()?;
+        let result = self.evaluate(interpreter);
+        match self.line() {
+            Some(line) => result.map_err(|err| err.at(line)),
+            None => result,
+        }
+    }
+
+    // the line errors raised by this expression are reported at, literals don't have one and
+    // leave it to whatever they're part of
+    fn line(&self) -> Option<u32> {
         match self {
-            Expr::Literal(literal) => Ok(literal),
-            Expr::Unary(unary) => {
-                match unary.op {
-                    UnaryOp::Minus => match unary.expr.interpret_ast()? {
-                        Literal::Number(n) => Ok(Literal::Number(-n)),
-                        other => Err(format!("Expected a number but got {}", other)),
-                    },
-                    UnaryOp::Bang => {
-                        match unary.expr.interpret_ast()? {
-                            // much stricter than the book's implementation
-                            Literal::Boolean(b) => Ok(Literal::Boolean(!b)),
-                            other => Err(format!("Expected a boolean value but got {}", other)),
-                        }
-                    }
+            Expr::Unary(unary) => Some(unary.line),
+            Expr::Binary(binary) => Some(binary.line),
+            Expr::Logical(logical) => Some(logical.line),
+            Expr::Comparison(comparison) => Some(comparison.line),
+            Expr::Conditional(conditional) => Some(conditional.line),
+            Expr::Variable(token) => Some(token.line),
+            Expr::Assign(assign) => Some(assign.name.line),
+            Expr::Call(call) => Some(call.line),
+            Expr::Get(get) => Some(get.name.line),
+            Expr::Index(index) => Some(index.line),
+            Expr::List(list) => Some(list.line),
+            Expr::Map(map) => Some(map.line),
+            Expr::IndexAssign(assign) => Some(assign.target.line),
+            Expr::Update(update) => update.target.line(),
+            Expr::Literal(_) | Expr::Sequence(_) | Expr::Grouping(_) | Expr::Function(_) => None,
+        }
+    }
+
+    fn evaluate(&self, interpreter: &mut Interpreter) -> Result<Literal, RuntimeError> {
+        match self {
+            Expr::Literal(literal) => Ok(literal.clone()),
+            Expr::List(list) => {
+                let items = list
+                    .items
+                    .iter()
+                    .map(|item| item.interpret_ast(interpreter))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(list::new_list(items))
+            }
+            Expr::Map(literal) => {
+                let mut map = OrderedMap::new();
+                for (key, val) in &literal.entries {
+                    let key = Key::try_from(&key.interpret_ast(interpreter)?)?;
+                    map.insert(key, val.interpret_ast(interpreter)?);
                 }
+                Ok(map::new_map(map))
             }
+            Expr::Unary(unary) => match unary.op {
+                UnaryOp::Minus => match unary.expr.interpret_ast(interpreter)? {
+                    Literal::Integer(i) => i.checked_neg().map(Literal::Integer).ok_or_else(|| {
+                        RuntimeError::new(
+                            ErrorType::ArithmeticError,
+                            format!("Integer overflow negating {}", i),
+                        )
+                    }),
+                    Literal::Number(n) => Ok(Literal::Number(-n)),
+                    other => Err(RuntimeError::new(
+                        ErrorType::TypeError,
+                        format!("Expected a number but got {}", other.describe()),
+                    )),
+                },
+                UnaryOp::Bang => {
+                    let value = unary.expr.interpret_ast(interpreter)?;
+                    Ok(Literal::Boolean(!interpreter.is_truthy(&value)?))
+                }
+                UnaryOp::Tilde => match unary.expr.interpret_ast(interpreter)? {
+                    Literal::Integer(i) => Ok(Literal::Integer(!i)),
+                    other => Err(RuntimeError::new(
+                        ErrorType::TypeError,
+                        format!(
+                            "Operator {} expects an integer but got {}",
+                            unary.op,
+                            other.describe()
+                        ),
+                    )),
+                },
+            },
             Expr::Binary(binary) => {
-                let l = binary.l_expr.interpret_ast()?;
-                let r = binary.r_expr.interpret_ast()?;
-                match (l, r) {
-                    (Literal::Number(ln), Literal::Number(rn)) => match binary.op {
-                        BinaryOp::EqualEqual => Ok(Literal::Boolean(ln == rn)),
-                        BinaryOp::BangEqual => Ok(Literal::Boolean(ln != rn)),
-                        BinaryOp::Less => Ok(Literal::Boolean(ln < rn)),
-                        BinaryOp::LessEqual => Ok(Literal::Boolean(ln <= rn)),
-                        BinaryOp::Greater => Ok(Literal::Boolean(ln > rn)),
-                        BinaryOp::GreaterEqual => Ok(Literal::Boolean(ln >= rn)),
-                        BinaryOp::Plus => Ok(Literal::Number(ln + rn)),
-                        BinaryOp::Minus => Ok(Literal::Number(ln - rn)),
-                        BinaryOp::Star => Ok(Literal::Number(ln * rn)),
-                        BinaryOp::Slash => Ok(Literal::Number(ln / rn)),
-                    },
-                    (Literal::String(ls), Literal::String(rs)) => match binary.op {
-                        BinaryOp::Plus => Ok(Literal::String(ls + &rs)),
-                        BinaryOp::EqualEqual => Ok(Literal::Boolean(ls == rs)),
-                        BinaryOp::BangEqual => Ok(Literal::Boolean(ls != rs)),
-                        bad_op => Err(format!("Operation {} not supported for Strings", bad_op)),
-                    },
-                    (Literal::Boolean(lb), Literal::Boolean(rb)) => match binary.op {
-                        BinaryOp::EqualEqual => Ok(Literal::Boolean(lb == rb)),
-                        BinaryOp::BangEqual => Ok(Literal::Boolean(lb != rb)),
-                        bad_op => Err(format!("Operation {} not supported for Booleans", bad_op)),
-                    },
-                    (Literal::Nil, Literal::Nil) => match binary.op {
-                        BinaryOp::EqualEqual => Ok(Literal::Boolean(true)),
-                        BinaryOp::BangEqual => Ok(Literal::Boolean(false)),
-                        bad_op => Err(format!("Operation {} not supported for Booleans", bad_op)),
-                    },
-                    (mismatch_l, mismatch_r) => match binary.op {
-                        BinaryOp::EqualEqual => Ok(Literal::Boolean(false)),
-                        BinaryOp::BangEqual => Ok(Literal::Boolean(true)),
-                        _ => Err(format!(
-                            "Mismatched types: left was {} while right was {}",
-                            mismatch_l, mismatch_r
-                        )),
-                    },
+                let l = binary.l_expr.interpret_ast(interpreter)?;
+                let r = binary.r_expr.interpret_ast(interpreter)?;
+                binary_op(interpreter.options(), &binary.op, l, r)
+            }
+            Expr::Logical(logical) => {
+                let l = logical.l_expr.interpret_ast(interpreter)?;
+                // short-circuits, the value of whichever side decided the result is returned
+                match (&logical.op, interpreter.is_truthy(&l)?) {
+                    (LogicalOp::Or, true) | (LogicalOp::And, false) => Ok(l),
+                    _ => {
+                        let r = logical.r_expr.interpret_ast(interpreter)?;
+                        interpreter.is_truthy(&r)?; // strict mode wants booleans on both sides
+                        Ok(r)
+                    }
+                }
+            }
+            Expr::Comparison(comparison) => {
+                let mut l = comparison.first.interpret_ast(interpreter)?;
+                for (op, expr) in &comparison.rest {
+                    let r = expr.interpret_ast(interpreter)?;
+                    match binary_op(interpreter.options(), op, l, r.clone())? {
+                        Literal::Boolean(true) => l = r,
+                        // stops at the first false link, later operands aren't evaluated
+                        _ => return Ok(Literal::Boolean(false)),
+                    }
                 }
+                Ok(Literal::Boolean(true))
             }
-            Expr::Grouping(grouping) => Ok(grouping.expr.interpret_ast()?),
-            Expr::Variable(token, map) => match token.token_type {
+            Expr::Conditional(conditional) => {
+                let condition = conditional.condition.interpret_ast(interpreter)?;
+                match interpreter.is_truthy(&condition)? {
+                    true => conditional.then_expr.interpret_ast(interpreter),
+                    false => conditional.else_expr.interpret_ast(interpreter),
+                }
+            }
+            Expr::Sequence(exprs) => {
+                // every expression is evaluated, only the last one's value is kept
+                let mut val = Literal::Nil;
+                for expr in exprs {
+                    val = expr.interpret_ast(interpreter)?;
+                }
+                Ok(val)
+            }
+            Expr::Grouping(grouping) => Ok(grouping.expr.interpret_ast(interpreter)?),
+            Expr::Variable(token) => match &token.token_type {
                 tokens::TokenType::Identifier => match token.literal.as_ref().unwrap() {
-                    tokens::Literal::Identifier(i) => map
-                        .borrow()
-                        .get(i)
-                        .ok_or("Couldn't find the variable".to_owned()),
+                    tokens::Literal::Identifier(i) => {
+                        interpreter.get(i).ok_or_else(|| undefined(i))
+                    }
                     _ => unreachable!("shouldn't ever be a number/string"),
                 },
-                ttype => Err(format!(
-                    "Somehow this Identifier was of type {:?} instead",
-                    ttype
-                )),
+                ttype => {
+                    Err(format!("Somehow this Identifier was of type {:?} instead", ttype).into())
+                }
             },
-            Expr::Assign(assign, map) => {
-                let val = assign.expr.interpret_ast()?;
-                map.borrow_mut().assign(assign.name.lexeme, val.clone())?;
+            Expr::Assign(assign) => {
+                let val = assign.expr.interpret_ast(interpreter)?;
+                interpreter.assign(assign.name.lexeme.to_string(), val.clone())?;
+                Ok(val)
+            }
+            Expr::Call(call) => {
+                let callee = call.callee.interpret_ast(interpreter)?;
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| arg.interpret_ast(interpreter))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match callee {
+                    Literal::Callable(callable) => callable.call(interpreter, args, call.line),
+                    other => Err(RuntimeError::new(
+                        ErrorType::TypeError,
+                        format!("Can only call functions but got {}", other.describe()),
+                    )),
+                }
+            }
+            Expr::Get(get) => {
+                let object = get.object.interpret_ast(interpreter)?;
+                if let Literal::Module(module) = &object {
+                    return module.get(&get.name.lexeme).ok_or_else(|| {
+                        RuntimeError::new(
+                            ErrorType::NameError,
+                            format!("Module {} has no member {}", module.name(), get.name.lexeme),
+                        )
+                    });
+                }
+                if let Literal::Error(error) = &object {
+                    return error.property(&get.name.lexeme).ok_or_else(|| {
+                        RuntimeError::new(
+                            ErrorType::TypeError,
+                            format!("{} has no property {}", object, get.name.lexeme),
+                        )
+                    });
+                }
+                let method = match &object {
+                    Literal::List(_) => list::method(&get.name.lexeme),
+                    Literal::Map(_) => map::method(&get.name.lexeme),
+                    Literal::String(_) => string::method(&get.name.lexeme),
+                    _ => None,
+                };
+                match method {
+                    Some(method) => Ok(Literal::Callable(Callable::Method(
+                        Box::new(object),
+                        method,
+                    ))),
+                    None => Err(RuntimeError::new(
+                        ErrorType::TypeError,
+                        format!("{} has no property {}", object, get.name.lexeme),
+                    )),
+                }
+            }
+            Expr::Index(index) => {
+                let object = index.object.interpret_ast(interpreter)?;
+                let at = index.index.interpret_ast(interpreter)?;
+                get_index(&object, &at)
+            }
+            Expr::IndexAssign(assign) => {
+                let object = assign.target.object.interpret_ast(interpreter)?;
+                let at = assign.target.index.interpret_ast(interpreter)?;
+                let val = assign.expr.interpret_ast(interpreter)?;
+                set_index(&object, &at, val.clone())?;
                 Ok(val)
             }
+            Expr::Function(function) => {
+                let closure = Closure::new(
+                    function.clone(),
+                    interpreter.environment(),
+                    interpreter.script(),
+                );
+                Ok(Literal::Callable(Callable::Function(Rc::new(closure))))
+            }
+            Expr::Update(update) => {
+                let (old, new) = match update.target.as_ref() {
+                    Expr::Variable(name) => {
+                        let old = interpreter
+                            .get(&name.lexeme)
+                            .ok_or_else(|| undefined(&name.lexeme))?;
+                        let val = update.value.interpret_ast(interpreter)?;
+                        let new = binary_op(interpreter.options(), &update.op, old.clone(), val)?;
+                        interpreter.assign(name.lexeme.to_string(), new.clone())?;
+                        (old, new)
+                    }
+                    Expr::Index(index) => {
+                        let object = index.object.interpret_ast(interpreter)?;
+                        let at = index.index.interpret_ast(interpreter)?;
+                        let old = get_index(&object, &at)?;
+                        let val = update.value.interpret_ast(interpreter)?;
+                        let new = binary_op(interpreter.options(), &update.op, old.clone(), val)?;
+                        set_index(&object, &at, new.clone())?;
+                        (old, new)
+                    }
+                    _ => unreachable!("parser only builds updates of variables and indices"),
+                };
+                match update.postfix {
+                    true => Ok(old),
+                    false => Ok(new),
+                }
+            }
+        }
+    }
+}
+
+fn undefined(name: &str) -> RuntimeError {
+    RuntimeError::new(
+        ErrorType::NameError,
+        format!("Undefined variable: {}", name),
+    )
+}
+
+fn binary_op(
+    options: &Options,
+    op: &BinaryOp,
+    l: Literal,
+    r: Literal,
+) -> Result<Literal, RuntimeError> {
+    match op {
+        BinaryOp::EqualEqual => return Ok(Literal::Boolean(values_equal(&l, &r))),
+        BinaryOp::BangEqual => return Ok(Literal::Boolean(!values_equal(&l, &r))),
+        _ => (),
+    }
+
+    match (l, r) {
+        (Literal::Integer(li), Literal::Integer(ri)) => integer_binary(options, op, li, ri),
+        // ordering between an integer and a float is exact, like values_equal, so exactly one
+        // of <, == and > holds unless the float is nan
+        (Literal::Integer(li), Literal::Number(rn)) if is_ordering(op) => {
+            Ok(ordering_result(op, compare_integer_float(li, rn)))
+        }
+        (Literal::Number(ln), Literal::Integer(ri)) if is_ordering(op) => Ok(ordering_result(
+            op,
+            compare_integer_float(ri, ln).map(Ordering::reverse),
+        )),
+        // bitwise operators only take integers, checked before promoting so the message still
+        // says which side was the float
+        (l @ (Literal::Integer(_) | Literal::Number(_)), r @ Literal::Number(_))
+        | (l @ Literal::Number(_), r @ Literal::Integer(_))
+            if is_bitwise(op) =>
+        {
+            Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!(
+                    "Operator {} expects integers but got {} and {}",
+                    op,
+                    l.describe(),
+                    r.describe()
+                ),
+            ))
+        }
+        // any other mix of integers and floats promotes the integer
+        (Literal::Integer(li), Literal::Number(rn)) => number_binary(options, op, li as f64, rn),
+        (Literal::Number(ln), Literal::Integer(ri)) => number_binary(options, op, ln, ri as f64),
+        (Literal::Number(ln), Literal::Number(rn)) => number_binary(options, op, ln, rn),
+        (Literal::String(ls), Literal::String(rs)) => match op {
+            BinaryOp::Plus => Ok(Literal::String(ls + &rs)),
+            // lexicographic, by unicode scalar value
+            BinaryOp::Less => Ok(Literal::Boolean(ls < rs)),
+            BinaryOp::LessEqual => Ok(Literal::Boolean(ls <= rs)),
+            BinaryOp::Greater => Ok(Literal::Boolean(ls > rs)),
+            BinaryOp::GreaterEqual => Ok(Literal::Boolean(ls >= rs)),
+            bad_op => Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("Operator {} not supported for strings", bad_op),
+            )),
+        },
+        // "count: " + 3, the non-string side is converted the same way print would show it
+        (Literal::String(ls), r) if *op == BinaryOp::Plus && !options.strict_concatenation => {
+            Ok(Literal::String(ls + &r.to_string()))
+        }
+        (l, Literal::String(rs)) if *op == BinaryOp::Plus && !options.strict_concatenation => {
+            Ok(Literal::String(l.to_string() + &rs))
+        }
+        (l, r) if l.type_name() == r.type_name() => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!("Operator {} not supported for {}s", op, l.type_name()),
+        )),
+        (l, r) => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!(
+                "Operator {} not supported between {} and {}",
+                op,
+                l.type_name(),
+                r.type_name()
+            ),
+        )),
+    }
+}
+
+fn is_ordering(op: &BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual
+    )
+}
+
+fn is_bitwise(op: &BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Ampersand
+            | BinaryOp::Pipe
+            | BinaryOp::Caret
+            | BinaryOp::LessLess
+            | BinaryOp::GreaterGreater
+    )
+}
+
+// None only when n is nan, which is unordered with everything
+fn compare_integer_float(i: i64, n: f64) -> Option<Ordering> {
+    if n.is_nan() {
+        return None;
+    }
+    // 2^63 as a float, every float below it and at or above -2^63 truncates into an i64
+    let limit = -(i64::MIN as f64);
+    if n >= limit {
+        return Some(Ordering::Less);
+    }
+    if n < -limit {
+        return Some(Ordering::Greater);
+    }
+    let whole = n.trunc();
+    Some(i.cmp(&(whole as i64)).then_with(|| {
+        // the same whole part, so the fraction decides
+        match n - whole {
+            fract if fract > 0.0 => Ordering::Less,
+            fract if fract < 0.0 => Ordering::Greater,
+            _ => Ordering::Equal,
         }
+    }))
+}
+
+fn ordering_result(op: &BinaryOp, ordering: Option<Ordering>) -> Literal {
+    let holds = match (op, ordering) {
+        (_, None) => false,
+        (BinaryOp::Less, Some(ordering)) => ordering.is_lt(),
+        (BinaryOp::LessEqual, Some(ordering)) => ordering.is_le(),
+        (BinaryOp::Greater, Some(ordering)) => ordering.is_gt(),
+        (BinaryOp::GreaterEqual, Some(ordering)) => ordering.is_ge(),
+        _ => unreachable!("only called for <, <=, > and >="),
+    };
+    Literal::Boolean(holds)
+}
+
+// The one equality relation behind `==` and `!=`, values of different types are never equal
+// except for numbers:
+// - integers and floats compare numerically, so 1 == 1.0, and nan is unequal to everything
+// - strings, booleans and nil compare by value
+// - lists and maps compare by identity, two lists with the same items are still different lists
+// - functions are equal when they're the same function, methods also need the same receiver
+// - errors and modules compare by identity
+pub fn values_equal(l: &Literal, r: &Literal) -> bool {
+    match (l, r) {
+        (Literal::Integer(li), Literal::Integer(ri)) => li == ri,
+        (Literal::Number(ln), Literal::Number(rn)) => ln == rn,
+        // exact, going through f64 alone would make 2^53 + 1 equal to 2^53
+        (Literal::Integer(i), Literal::Number(n)) | (Literal::Number(n), Literal::Integer(i)) => {
+            *i as f64 == *n && *n as i128 == *i as i128
+        }
+        (Literal::String(ls), Literal::String(rs)) => ls == rs,
+        (Literal::Boolean(lb), Literal::Boolean(rb)) => lb == rb,
+        (Literal::Nil, Literal::Nil) => true,
+        (Literal::List(ll), Literal::List(rl)) => Rc::ptr_eq(ll, rl),
+        (Literal::Map(lm), Literal::Map(rm)) => Rc::ptr_eq(lm, rm),
+        (Literal::Callable(lc), Literal::Callable(rc)) => lc.same_as(rc),
+        (Literal::Error(le), Literal::Error(re)) => Rc::ptr_eq(le, re),
+        (Literal::Module(lm), Literal::Module(rm)) => Rc::ptr_eq(lm, rm),
+        _ => false,
     }
 }
 
+fn get_index(object: &Literal, at: &Literal) -> Result<Literal, RuntimeError> {
+    match object {
+        Literal::List(list) => {
+            let list = list.borrow();
+            let at = natives::index("list", list.len(), at)?;
+            Ok(list[at].clone())
+        }
+        Literal::Map(map) => {
+            let key = Key::try_from(at)?;
+            map.borrow().get(&key).cloned().ok_or_else(|| {
+                RuntimeError::new(ErrorType::IndexError, format!("Map has no key {}", key))
+            })
+        }
+        Literal::String(s) => string::char_at(s, at),
+        other => Err(not_indexable(other)),
+    }
+}
+
+fn set_index(object: &Literal, at: &Literal, val: Literal) -> Result<(), RuntimeError> {
+    match object {
+        Literal::List(list) => {
+            let mut list = list.borrow_mut();
+            let at = natives::index("list", list.len(), at)?;
+            list[at] = val;
+            Ok(())
+        }
+        Literal::Map(map) => {
+            map.borrow_mut().insert(Key::try_from(at)?, val);
+            Ok(())
+        }
+        Literal::String(_) => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            "Strings can't be changed, build a new one instead".to_owned(),
+        )),
+        other => Err(not_indexable(other)),
+    }
+}
+
+fn not_indexable(object: &Literal) -> RuntimeError {
+    RuntimeError::new(
+        ErrorType::TypeError,
+        format!(
+            "Can only index into lists, maps and strings but got {}",
+            object.describe()
+        ),
+    )
+}
+
 #[derive(Debug, Clone)]
 pub enum Literal {
+    Integer(i64),
     Number(f64),
     String(String),
     Boolean(bool),
     Nil,
+    List(List),
+    Map(Map),
+    Callable(Callable),
+    Error(Rc<ErrorObject>),
+    Module(Rc<Module>),
+}
+
+impl Literal {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Literal::Integer(_) => "integer",
+            Literal::Number(_) => "number",
+            Literal::String(_) => "string",
+            Literal::Boolean(_) => "boolean",
+            Literal::Nil => "nil",
+            Literal::List(_) => "list",
+            Literal::Map(_) => "map",
+            Literal::Callable(_) => "function",
+            Literal::Error(_) => "error",
+            Literal::Module(_) => "module",
+        }
+    }
+
+    // the type and the value, for messages about a value of the wrong type. Whole floats print
+    // like integers, so `1` alone wouldn't say which one it was
+    pub fn describe(&self) -> String {
+        match self {
+            Literal::Nil => "nil".to_owned(),
+            Literal::String(s) => format!("string \"{}\"", s),
+            other => format!("{} {}", other.type_name(), other),
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&List> {
+        match self {
+            Literal::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self {
+            Literal::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Literal::String(s) => Some(s),
+            _ => None,
+        }
+    }
 }
 
+// floats whose magnitude is outside EXPONENT_BELOW..EXPONENT_FROM print in exponent form
+const EXPONENT_BELOW: f64 = 1e-4;
+const EXPONENT_FROM: f64 = 1e16;
+
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Literal::Integer(i) => write!(f, "{}", i),
+            // whole floats print without a trailing .0, nan and infinities in lowercase. Very
+            // large and very small ones print with an exponent (1e30, 2.5e-7) rather than every
+            // digit of their expansion
+            Literal::Number(n) if n.is_nan() => write!(f, "nan"),
+            Literal::Number(n) if n.is_infinite() && *n > 0.0 => write!(f, "inf"),
+            Literal::Number(n) if n.is_infinite() => write!(f, "-inf"),
+            Literal::Number(n)
+                if *n != 0.0 && !(EXPONENT_BELOW..EXPONENT_FROM).contains(&n.abs()) =>
+            {
+                write!(f, "{:e}", n)
+            }
             Literal::Number(n) => write!(f, "{}", n),
             Literal::String(s) => write!(f, "{}", s),
             Literal::Boolean(b) => write!(f, "{}", b),
             Literal::Nil => write!(f, "nil"),
+            Literal::List(list) => fmt_list(f, list, &mut HashSet::new()),
+            Literal::Map(map) => fmt_map(f, map, &mut HashSet::new()),
+            Literal::Callable(callable) => write!(f, "{}", callable),
+            Literal::Error(error) => write!(f, "{}", error),
+            Literal::Module(module) => write!(f, "{}", module),
         }
     }
 }
 
+// lists and maps are shared, so one can contain itself. `printing` holds the collections
+// further out that are still being printed, running into one of them again prints [...] or
+// {...} instead of recursing
+fn fmt_list(
+    f: &mut fmt::Formatter<'_>,
+    list: &List,
+    printing: &mut HashSet<*const ()>,
+) -> fmt::Result {
+    let ptr = Rc::as_ptr(list).cast();
+    if !printing.insert(ptr) {
+        return write!(f, "[...]");
+    }
+    write!(f, "[")?;
+    for (i, item) in list.borrow().iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        fmt_nested(f, item, printing)?;
+    }
+    printing.remove(&ptr);
+    write!(f, "]")
+}
+
+fn fmt_map(
+    f: &mut fmt::Formatter<'_>,
+    map: &Map,
+    printing: &mut HashSet<*const ()>,
+) -> fmt::Result {
+    let ptr = Rc::as_ptr(map).cast();
+    if !printing.insert(ptr) {
+        return write!(f, "{{...}}");
+    }
+    write!(f, "{{")?;
+    for (i, (key, val)) in map.borrow().iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: ", key)?;
+        fmt_nested(f, val, printing)?;
+    }
+    printing.remove(&ptr);
+    write!(f, "}}")
+}
+
+// strings inside a collection are quoted so ["a, b"] and ["a", "b"] don't print the same
+fn fmt_nested(
+    f: &mut fmt::Formatter<'_>,
+    item: &Literal,
+    printing: &mut HashSet<*const ()>,
+) -> fmt::Result {
+    match item {
+        Literal::String(s) => write!(f, "\"{}\"", s),
+        Literal::List(list) => fmt_list(f, list, printing),
+        Literal::Map(map) => fmt_map(f, map, printing),
+        other => write!(f, "{}", other),
+    }
+}
+
 pub enum UnaryOp {
     Minus,
     Bang,
+    Tilde,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Minus => write!(f, "-"),
+            UnaryOp::Bang => write!(f, "!"),
+            UnaryOp::Tilde => write!(f, "~"),
+        }
+    }
 }
 
 pub struct Unary {
     op: UnaryOp,
     expr: Box<Expr>,
+    line: u32,
 }
 
 impl Unary {
-    pub fn new(op: UnaryOp, expr: Box<Expr>) -> Self {
-        Self { op, expr }
+    pub fn new(op: UnaryOp, expr: Box<Expr>, line: u32) -> Self {
+        Self { op, expr, line }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub enum BinaryOp {
     EqualEqual,
     BangEqual,
@@ -220,21 +758,158 @@ pub enum BinaryOp {
     Minus,
     Star,
     Slash,
+    Percent,
+    TildeSlash,
+    StarStar,
+    Ampersand,
+    Pipe,
+    Caret,
+    LessLess,
+    GreaterGreater,
 }
 
 impl fmt::Display for BinaryOp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            BinaryOp::EqualEqual => write!(f, "{}", "=="),
-            BinaryOp::BangEqual => write!(f, "{}", "!="),
-            BinaryOp::Less => write!(f, "{}", "<"),
-            BinaryOp::LessEqual => write!(f, "{}", "<="),
-            BinaryOp::Greater => write!(f, "{}", ">"),
-            BinaryOp::GreaterEqual => write!(f, "{}", ">="),
-            BinaryOp::Plus => write!(f, "{}", "+"),
-            BinaryOp::Minus => write!(f, "{}", "-"),
-            BinaryOp::Star => write!(f, "{}", "*"),
-            BinaryOp::Slash => write!(f, "{}", "/"),
+            BinaryOp::EqualEqual => write!(f, "=="),
+            BinaryOp::BangEqual => write!(f, "!="),
+            BinaryOp::Less => write!(f, "<"),
+            BinaryOp::LessEqual => write!(f, "<="),
+            BinaryOp::Greater => write!(f, ">"),
+            BinaryOp::GreaterEqual => write!(f, ">="),
+            BinaryOp::Plus => write!(f, "+"),
+            BinaryOp::Minus => write!(f, "-"),
+            BinaryOp::Star => write!(f, "*"),
+            BinaryOp::Slash => write!(f, "/"),
+            BinaryOp::Percent => write!(f, "%"),
+            BinaryOp::TildeSlash => write!(f, "~/"),
+            BinaryOp::StarStar => write!(f, "**"),
+            BinaryOp::Ampersand => write!(f, "&"),
+            BinaryOp::Pipe => write!(f, "|"),
+            BinaryOp::Caret => write!(f, "^"),
+            BinaryOp::LessLess => write!(f, "<<"),
+            BinaryOp::GreaterGreater => write!(f, ">>"),
+        }
+    }
+}
+
+// integer arithmetic is checked, overflowing is an error rather than wrapping or promoting
+fn integer_binary(
+    options: &Options,
+    op: &BinaryOp,
+    l: i64,
+    r: i64,
+) -> Result<Literal, RuntimeError> {
+    let overflow = || {
+        RuntimeError::new(
+            ErrorType::ArithmeticError,
+            format!("Integer overflow in {} {} {}", l, op, r),
+        )
+    };
+    match op {
+        BinaryOp::EqualEqual | BinaryOp::BangEqual => {
+            unreachable!("equality is handled by values_equal")
+        }
+        BinaryOp::Less => Ok(Literal::Boolean(l < r)),
+        BinaryOp::LessEqual => Ok(Literal::Boolean(l <= r)),
+        BinaryOp::Greater => Ok(Literal::Boolean(l > r)),
+        BinaryOp::GreaterEqual => Ok(Literal::Boolean(l >= r)),
+        BinaryOp::Plus => l.checked_add(r).map(Literal::Integer).ok_or_else(overflow),
+        BinaryOp::Minus => l.checked_sub(r).map(Literal::Integer).ok_or_else(overflow),
+        BinaryOp::Star => l.checked_mul(r).map(Literal::Integer).ok_or_else(overflow),
+        // `/` is always true division, `~/` is the integer one
+        BinaryOp::Slash => number_binary(options, op, l as f64, r as f64),
+        BinaryOp::Percent | BinaryOp::TildeSlash if r == 0 => Err(RuntimeError::new(
+            ErrorType::ArithmeticError,
+            format!("Integer division by zero in {} {} {}", l, op, r),
+        )),
+        // both round towards negative infinity, so l == (l ~/ r) * r + l % r always holds
+        // -1 divides everything, and i64::MIN % -1 would trip the overflow check in `%` even
+        // though the answer fits
+        BinaryOp::Percent if r == -1 => Ok(Literal::Integer(0)),
+        BinaryOp::Percent => {
+            let rem = l % r;
+            match rem != 0 && (rem < 0) != (r < 0) {
+                true => Ok(Literal::Integer(rem + r)),
+                false => Ok(Literal::Integer(rem)),
+            }
+        }
+        // i64::MIN ~/ -1 is the one quotient that doesn't fit
+        BinaryOp::TildeSlash => {
+            let quot = l.checked_div(r).ok_or_else(overflow)?;
+            match l % r != 0 && (l < 0) != (r < 0) {
+                true => Ok(Literal::Integer(quot - 1)),
+                false => Ok(Literal::Integer(quot)),
+            }
+        }
+        // a negative exponent can't give an integer back
+        BinaryOp::StarStar if r < 0 => Ok(Literal::Number((l as f64).powf(r as f64))),
+        BinaryOp::StarStar => u32::try_from(r)
+            .ok()
+            .and_then(|r| l.checked_pow(r))
+            .map(Literal::Integer)
+            .ok_or_else(overflow),
+        BinaryOp::Ampersand => Ok(Literal::Integer(l & r)),
+        BinaryOp::Pipe => Ok(Literal::Integer(l | r)),
+        BinaryOp::Caret => Ok(Literal::Integer(l ^ r)),
+        BinaryOp::LessLess | BinaryOp::GreaterGreater if !(0..64).contains(&r) => {
+            Err(RuntimeError::new(
+                ErrorType::ArithmeticError,
+                format!("Shift amount must be between 0 and 63 but got {}", r),
+            ))
+        }
+        // bits shifted past either end are dropped, >> keeps the sign
+        BinaryOp::LessLess => Ok(Literal::Integer(l << r)),
+        BinaryOp::GreaterGreater => Ok(Literal::Integer(l >> r)),
+    }
+}
+
+fn number_binary(
+    options: &Options,
+    op: &BinaryOp,
+    l: f64,
+    r: f64,
+) -> Result<Literal, RuntimeError> {
+    match op {
+        BinaryOp::Slash | BinaryOp::Percent | BinaryOp::TildeSlash
+            if r == 0.0 && options.division_by_zero == DivisionByZero::Error =>
+        {
+            Err(RuntimeError::new(
+                ErrorType::ArithmeticError,
+                format!(
+                    "Division by zero in {} {} {}",
+                    Literal::Number(l),
+                    op,
+                    Literal::Number(r)
+                ),
+            ))
+        }
+        BinaryOp::EqualEqual | BinaryOp::BangEqual => {
+            unreachable!("equality is handled by values_equal")
+        }
+        BinaryOp::Less => Ok(Literal::Boolean(l < r)),
+        BinaryOp::LessEqual => Ok(Literal::Boolean(l <= r)),
+        BinaryOp::Greater => Ok(Literal::Boolean(l > r)),
+        BinaryOp::GreaterEqual => Ok(Literal::Boolean(l >= r)),
+        BinaryOp::Plus => Ok(Literal::Number(l + r)),
+        BinaryOp::Minus => Ok(Literal::Number(l - r)),
+        BinaryOp::Star => Ok(Literal::Number(l * r)),
+        BinaryOp::Slash => Ok(Literal::Number(l / r)),
+        BinaryOp::Percent => {
+            let rem = l % r;
+            match rem != 0.0 && (rem < 0.0) != (r < 0.0) {
+                true => Ok(Literal::Number(rem + r)),
+                false => Ok(Literal::Number(rem)),
+            }
+        }
+        BinaryOp::TildeSlash => Ok(Literal::Number((l / r).floor())),
+        BinaryOp::StarStar => Ok(Literal::Number(l.powf(r))),
+        BinaryOp::Ampersand
+        | BinaryOp::Pipe
+        | BinaryOp::Caret
+        | BinaryOp::LessLess
+        | BinaryOp::GreaterGreater => {
+            unreachable!("bitwise operators on floats are rejected first")
         }
     }
 }
@@ -243,11 +918,100 @@ pub struct Binary {
     l_expr: Box<Expr>,
     op: BinaryOp,
     r_expr: Box<Expr>,
+    line: u32,
 }
 
 impl Binary {
-    pub fn new(l_expr: Box<Expr>, op: BinaryOp, r_expr: Box<Expr>) -> Self {
-        Self { l_expr, op, r_expr }
+    pub fn new(l_expr: Box<Expr>, op: BinaryOp, r_expr: Box<Expr>, line: u32) -> Self {
+        Self {
+            l_expr,
+            op,
+            r_expr,
+            line,
+        }
+    }
+}
+
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+pub struct Logical {
+    l_expr: Box<Expr>,
+    op: LogicalOp,
+    r_expr: Box<Expr>,
+    line: u32,
+}
+
+impl Logical {
+    pub fn new(l_expr: Box<Expr>, op: LogicalOp, r_expr: Box<Expr>, line: u32) -> Self {
+        Self {
+            l_expr,
+            op,
+            r_expr,
+            line,
+        }
+    }
+}
+
+// `a < b <= c` is `a < b and b <= c`, with b only evaluated once
+pub struct Comparison {
+    first: Box<Expr>,
+    rest: Vec<(BinaryOp, Expr)>,
+    line: u32,
+}
+
+impl Comparison {
+    pub fn new(first: Box<Expr>, rest: Vec<(BinaryOp, Expr)>, line: u32) -> Self {
+        Self { first, rest, line }
+    }
+}
+
+pub struct Conditional {
+    condition: Box<Expr>,
+    then_expr: Box<Expr>,
+    else_expr: Box<Expr>,
+    line: u32,
+}
+
+impl Conditional {
+    pub fn new(
+        condition: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+        line: u32,
+    ) -> Self {
+        Self {
+            condition,
+            then_expr,
+            else_expr,
+            line,
+        }
+    }
+}
+
+// `[a, b]`, `line` is the opening bracket's
+pub struct ListLiteral {
+    items: Vec<Expr>,
+    line: u32,
+}
+
+impl ListLiteral {
+    pub fn new(items: Vec<Expr>, line: u32) -> Self {
+        Self { items, line }
+    }
+}
+
+// `{k: v}`, `line` is the opening brace's
+pub struct MapLiteral {
+    entries: Vec<(Expr, Expr)>,
+    line: u32,
+}
+
+impl MapLiteral {
+    pub fn new(entries: Vec<(Expr, Expr)>, line: u32) -> Self {
+        Self { entries, line }
     }
 }
 
@@ -271,3 +1035,145 @@ impl Assign {
         Self { name, expr }
     }
 }
+
+pub struct Call {
+    callee: Box<Expr>,
+    args: Vec<Expr>,
+    line: u32,
+}
+
+impl Call {
+    pub fn new(callee: Box<Expr>, args: Vec<Expr>, line: u32) -> Self {
+        Self { callee, args, line }
+    }
+}
+
+pub struct Get {
+    object: Box<Expr>,
+    name: Token,
+}
+
+impl Get {
+    pub fn new(object: Box<Expr>, name: Token) -> Self {
+        Self { object, name }
+    }
+}
+
+pub struct Index {
+    object: Box<Expr>,
+    index: Box<Expr>,
+    line: u32,
+}
+
+impl Index {
+    pub fn new(object: Box<Expr>, index: Box<Expr>, line: u32) -> Self {
+        Self {
+            object,
+            index,
+            line,
+        }
+    }
+}
+
+pub struct IndexAssign {
+    target: Index,
+    expr: Box<Expr>,
+}
+
+impl IndexAssign {
+    pub fn new(target: Index, expr: Box<Expr>) -> Self {
+        Self { target, expr }
+    }
+}
+
+// compound assignment (`x += 1`) and increments (`x++`), done in place so the parts of the
+// target (e.g. the list and index in `xs[f()] += 1`) are only evaluated once
+pub struct Update {
+    target: Box<Expr>, // always a Variable or an Index
+    op: BinaryOp,
+    value: Box<Expr>,
+    postfix: bool, // evaluates to the value from before the update
+}
+
+impl Update {
+    pub fn new(target: Expr, op: BinaryOp, value: Expr, postfix: bool) -> Self {
+        Self {
+            target: Box::new(target),
+            op,
+            value: Box::new(value),
+            postfix,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_containing_itself_prints() {
+        let xs = list::new_list(vec![Literal::Integer(1)]);
+        if let Literal::List(list) = &xs {
+            list.borrow_mut().push(xs.clone());
+        }
+        assert_eq!(xs.to_string(), "[1, [...]]");
+    }
+
+    #[test]
+    fn map_containing_itself_prints() {
+        let m = map::new_map(OrderedMap::new());
+        if let Literal::Map(map) = &m {
+            map.borrow_mut()
+                .insert(Key::String("self".to_owned()), m.clone());
+        }
+        assert_eq!(m.to_string(), r#"{"self": {...}}"#);
+    }
+
+    #[test]
+    fn floats_print_whole_or_with_an_exponent() {
+        assert_eq!(Literal::Number(3.0).to_string(), "3");
+        assert_eq!(Literal::Number(0.25).to_string(), "0.25");
+        assert_eq!(Literal::Number(1e30).to_string(), "1e30");
+        assert_eq!(Literal::Number(-2.5e-7).to_string(), "-2.5e-7");
+        assert_eq!(
+            Literal::Number(9007199254740992.0).to_string(),
+            "9007199254740992"
+        );
+    }
+
+    #[test]
+    fn described_values_say_their_type() {
+        assert_eq!(Literal::Number(1.0).describe(), "number 1");
+        assert_eq!(Literal::Integer(1).describe(), "integer 1");
+        assert_eq!(Literal::String("1".to_owned()).describe(), "string \"1\"");
+        assert_eq!(Literal::Nil.describe(), "nil");
+    }
+
+    #[test]
+    fn integer_float_ordering_is_exact() {
+        let big = 9007199254740993; // 2^53 + 1, which no f64 holds
+        assert_eq!(
+            compare_integer_float(big, 9007199254740992.0),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare_integer_float(1, 1.5), Some(Ordering::Less));
+        assert_eq!(compare_integer_float(-1, -1.5), Some(Ordering::Greater));
+        assert_eq!(
+            compare_integer_float(i64::MAX, 2f64.powi(63)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare_integer_float(i64::MIN, -(2f64.powi(63))),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(compare_integer_float(0, f64::NAN), None);
+    }
+
+    #[test]
+    fn integer_remainder_by_minus_one() {
+        let options = Options::default();
+        let rem = integer_binary(&options, &BinaryOp::Percent, i64::MIN, -1).unwrap();
+        assert!(values_equal(&rem, &Literal::Integer(0)));
+        assert!(integer_binary(&options, &BinaryOp::TildeSlash, i64::MIN, -1).is_err());
+    }
+}
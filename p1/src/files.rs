@@ -0,0 +1,97 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    callable::NativeFn,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    interpreter::Interpreter,
+    list, natives,
+};
+
+// natives for files and the standard streams, failures are IOErrors a script can catch
+pub fn natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn::new("readFile", 1, read_file),
+        NativeFn::new("writeFile", 2, write_file),
+        NativeFn::new("appendFile", 2, append_file),
+        NativeFn::new("readLines", 1, read_lines),
+        NativeFn::new("fileExists", 1, file_exists),
+        NativeFn::new("readLine", 0, read_line),
+        NativeFn::new("printErr", 1, print_err),
+    ]
+}
+
+fn io_error(action: &str, path: &str, err: io::Error) -> RuntimeError {
+    RuntimeError::new(
+        ErrorType::IOError,
+        format!("Couldn't {} {}: {}", action, path, err),
+    )
+}
+
+fn read_file(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let path = natives::string_arg("readFile", &args[0])?;
+    let contents = fs::read_to_string(path).map_err(|err| io_error("read", path, err))?;
+    Ok(Literal::String(contents))
+}
+
+// creates the file, or replaces what was in it
+fn write_file(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let path = natives::string_arg("writeFile", &args[0])?;
+    let contents = natives::string_arg("writeFile", &args[1])?;
+    fs::write(path, contents).map_err(|err| io_error("write", path, err))?;
+    Ok(Literal::Nil)
+}
+
+// creates the file if it isn't there yet
+fn append_file(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let path = natives::string_arg("appendFile", &args[0])?;
+    let contents = natives::string_arg("appendFile", &args[1])?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|err| io_error("append to", path, err))?;
+    Ok(Literal::Nil)
+}
+
+// a list of the lines without their line endings
+fn read_lines(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let path = natives::string_arg("readLines", &args[0])?;
+    let contents = fs::read_to_string(path).map_err(|err| io_error("read", path, err))?;
+    let lines = contents
+        .lines()
+        .map(|line| Literal::String(line.to_owned()))
+        .collect();
+    Ok(list::new_list(lines))
+}
+
+fn file_exists(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let path = natives::string_arg("fileExists", &args[0])?;
+    Ok(Literal::Boolean(Path::new(path).is_file()))
+}
+
+// the next line from stdin without its line ending, nil once stdin is closed
+fn read_line(_: &mut Interpreter, _: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    let read = io::stdin()
+        .read_line(&mut line)
+        .map_err(|err| io_error("read", "stdin", err))?;
+    if read == 0 {
+        return Ok(Literal::Nil);
+    }
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Literal::String(line))
+}
+
+// the same as print, but to stderr
+fn print_err(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    eprintln!("{}", args[0]);
+    Ok(Literal::Nil)
+}
@@ -0,0 +1,378 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    callable::Callable,
+    environment::Environment,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    list,
+    math::Random,
+    module::Module,
+    natives,
+    parser::Parser,
+    scanner::Scanner,
+    stmt::{Flow, Stmt},
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    // only booleans are accepted by `!`, conditions and `and`/`or`, instead of nil/false being falsey
+    pub strict_truthiness: bool,
+    pub division_by_zero: DivisionByZero,
+    // `+` only concatenates two strings, instead of converting the other side when one is a string
+    pub strict_concatenation: bool,
+    // where imports are looked for when they aren't next to the importing file, in order
+    pub module_path: Vec<PathBuf>,
+}
+
+// what float `/`, `%` and `~/` do when the right side is 0 (integer `%` and `~/` always error)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum DivisionByZero {
+    #[default]
+    Ieee, // inf, -inf or nan, as IEEE 754 says
+    Error,
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>, // innermost scope, blocks swap in a child for their duration
+    globals: Rc<RefCell<Environment>>,     // natives and `args`, what every script's scope encloses
+    options: Options,
+    script: Rc<str>, // file the running code is from, for stack traces and relative imports
+    frames: Vec<Frame>,
+    modules: HashMap<PathBuf, Rc<Module>>, // already imported, by canonical path
+    importing: Vec<PathBuf>,               // imports in progress, to catch cycles
+    random: Random,                        // behind math.random()
+    stack_base: usize, // roughly where the interpreter thread's stack starts, see check_stack
+}
+
+// a call in progress
+struct Frame {
+    name: String,
+    line: u32,       // where it was called from
+    script: Rc<str>, // and in which file
+}
+
+// deeper recursion than this is an error, whatever stack is left
+const MAX_FRAMES: usize = 1000;
+
+// the native stack the interpreter runs on, main.rs starts the interpreter's thread with it
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// kept free for what runs between two checks, and for reporting the error once one fails
+const STACK_RESERVE: usize = 4 * 1024 * 1024;
+
+impl Interpreter {
+    // `args` are the command line arguments after the script, the global `args` list
+    pub fn new(options: Options, script: Rc<str>, args: Vec<String>) -> Self {
+        // the interpreter is created near the top of its thread's stack, everything it runs
+        // is deeper than this
+        let stack_top = 0u8;
+        let mut globals = Environment::new();
+        for native in natives::globals() {
+            globals
+                .define(
+                    native.name().to_owned(),
+                    Literal::Callable(Callable::Native(native)),
+                )
+                .expect("no global is constant yet");
+        }
+        let args = args.into_iter().map(Literal::String).collect();
+        globals
+            .define("args".to_owned(), list::new_list(args))
+            .expect("no global is constant yet");
+
+        let globals = Rc::new(RefCell::new(globals));
+        // the entry script gets its own scope like every module, so what it defines doesn't
+        // leak into the modules it imports
+        // the entry script is in progress too, so importing it from itself is a cycle. "<stdin>"
+        // isn't a file, nothing is added for the prompt
+        let importing = fs::canonicalize(&*script).into_iter().collect();
+        let environment = Rc::new(RefCell::new(Environment::with_enclosing(globals.clone())));
+        Self {
+            environment,
+            globals,
+            options,
+            script,
+            frames: vec![],
+            modules: HashMap::new(),
+            importing,
+            random: Random::from_time(),
+            stack_base: std::ptr::addr_of!(stack_top) as usize,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn get(&self, key: &str) -> Option<Literal> {
+        self.environment.borrow().get(key)
+    }
+
+    pub fn define(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        self.environment.borrow_mut().define(key, val)
+    }
+
+    pub fn define_const(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        self.environment.borrow_mut().define_const(key, val)
+    }
+
+    pub fn assign(&mut self, key: String, val: Literal) -> Result<(), RuntimeError> {
+        self.environment.borrow_mut().assign(key, val)
+    }
+
+    pub fn execute_block(&mut self, stmts: &[Stmt]) -> Result<Flow, RuntimeError> {
+        let scope = Environment::with_enclosing(self.environment.clone());
+        self.execute_block_in(stmts, scope)
+    }
+
+    // for blocks that start out with names already defined, like a catch clause's error
+    pub fn execute_block_in(
+        &mut self,
+        stmts: &[Stmt],
+        scope: Environment,
+    ) -> Result<Flow, RuntimeError> {
+        self.with_environment(Rc::new(RefCell::new(scope)), |interpreter| {
+            interpreter.execute_stmts(stmts)
+        })
+    }
+
+    // runs `f` with `environment` as the innermost scope, then puts the current one back
+    pub fn with_environment<T>(
+        &mut self,
+        environment: Rc<RefCell<Environment>>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let enclosing = std::mem::replace(&mut self.environment, environment);
+
+        let result = f(self);
+
+        self.environment = enclosing;
+        result
+    }
+
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        self.environment.clone()
+    }
+
+    // a break or continue skips the rest of the block and is handed up to the loop
+    pub fn execute_stmts(&mut self, stmts: &[Stmt]) -> Result<Flow, RuntimeError> {
+        for stmt in stmts {
+            match stmt.interpret_stmt(self)? {
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn script(&self) -> Rc<str> {
+        self.script.clone()
+    }
+
+    // how deep the native stack gets depends on how the script nests, not just on its calls
+    // (a few blocks inside each recursive call are enough to run out before MAX_FRAMES), so
+    // every statement and expression checks what is left. Only works on the thread the
+    // interpreter was created on
+    pub fn check_stack(&self) -> Result<(), RuntimeError> {
+        let here = 0u8;
+        let used = self.stack_base.abs_diff(std::ptr::addr_of!(here) as usize);
+        match used > STACK_SIZE - STACK_RESERVE {
+            true => Err(RuntimeError::new(
+                ErrorType::StackOverflowError,
+                "Stack overflow, the script nests too deeply".to_owned(),
+            )),
+            false => Ok(()),
+        }
+    }
+
+    // `script` is the file the called code is from, natives run in their caller's
+    pub fn push_frame(
+        &mut self,
+        name: &str,
+        line: u32,
+        script: Option<Rc<str>>,
+    ) -> Result<(), RuntimeError> {
+        if self.frames.len() >= MAX_FRAMES {
+            return Err(RuntimeError::new(
+                ErrorType::StackOverflowError,
+                format!("Stack overflow, calls nested more than {} deep", MAX_FRAMES),
+            ));
+        }
+        let caller = match script {
+            Some(script) => std::mem::replace(&mut self.script, script),
+            None => self.script.clone(),
+        };
+        self.frames.push(Frame {
+            name: name.to_owned(),
+            line,
+            script: caller,
+        });
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            self.script = frame.script;
+        }
+    }
+
+    // records the calls in progress on an error that doesn't have them yet, called where the
+    // error leaves a call (and at the top level) while the frames are still there
+    pub fn traced(&self, err: RuntimeError) -> RuntimeError {
+        if err.has_trace() {
+            return err;
+        }
+
+        // each frame ran up to where it called the next one, the innermost up to the error
+        let names = std::iter::once("<script>").chain(self.frames.iter().map(|f| f.name.as_str()));
+        let lines = self
+            .frames
+            .iter()
+            .map(|frame| (Some(frame.line), &frame.script))
+            .chain(std::iter::once((err.line(), &self.script)));
+        let mut trace: Vec<String> = names
+            .zip(lines)
+            .map(|(name, (line, script))| match line {
+                Some(line) => format!("{} ({}:{})", name, script, line),
+                None => format!("{} ({})", name, script),
+            })
+            .collect();
+        trace.reverse();
+        err.with_trace(trace)
+    }
+
+    // runs the file the first time it's imported, later imports get the same module back
+    pub fn import(&mut self, path: &str, line: u32) -> Result<Rc<Module>, RuntimeError> {
+        if let Some(environment) = natives::builtin_module(path) {
+            // cached by name, which can't clash with the absolute paths files are cached by
+            let module = self.modules.entry(PathBuf::from(path)).or_insert_with(|| {
+                Rc::new(Module::new(
+                    path.to_owned(),
+                    Rc::new(RefCell::new(environment)),
+                ))
+            });
+            return Ok(module.clone());
+        }
+
+        let (script, canonical) = self.resolve_import(path)?;
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.importing.iter().position(|p| *p == canonical) {
+            let cycle: Vec<String> = self.importing[start..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(import_error(format!(
+                "Import cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        let source = fs::read_to_string(&canonical)
+            .map_err(|err| import_error(format!("Couldn't read {}: {}", script.display(), err)))?;
+        let script: Rc<str> = script.display().to_string().into();
+        let stmts = parse_module(&source, &script)?;
+
+        let environment = Rc::new(RefCell::new(Environment::with_enclosing(
+            self.globals.clone(),
+        )));
+        self.importing.push(canonical.clone());
+        // running the file shows up in stack traces like a call
+        let result = self
+            .push_frame("<module>", line, Some(script))
+            .and_then(|_| {
+                let result = self.with_environment(environment.clone(), |interpreter| {
+                    interpreter.execute_stmts(&stmts)
+                });
+                let result = result.map_err(|err| self.traced(err));
+                self.pop_frame();
+                result
+            });
+        self.importing.pop();
+        result?;
+
+        let name = canonical
+            .file_stem()
+            .map_or(path.to_owned(), |stem| stem.to_string_lossy().into_owned());
+        let module = Rc::new(Module::new(name, environment));
+        self.modules.insert(canonical, module.clone());
+        Ok(module)
+    }
+
+    // `util` means util.lox, looked for next to the importing file and then along the module path
+    fn resolve_import(&self, path: &str) -> Result<(PathBuf, PathBuf), RuntimeError> {
+        let mut file = PathBuf::from(path);
+        if file.extension().is_none() {
+            file.set_extension("lox");
+        }
+
+        let importer_dir = Path::new(&*self.script).parent().unwrap_or(Path::new(""));
+        let script = std::iter::once(importer_dir)
+            .chain(self.options.module_path.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&file))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| import_error(format!("Couldn't find module \"{}\"", path)))?;
+        let canonical = script
+            .canonicalize()
+            .map_err(|err| import_error(format!("Couldn't read {}: {}", script.display(), err)))?;
+        Ok((script, canonical))
+    }
+
+    // nil and false are falsey, everything else is truthy (unless running in strict mode)
+    pub fn is_truthy(&self, value: &Literal) -> Result<bool, RuntimeError> {
+        match value {
+            Literal::Boolean(b) => Ok(*b),
+            other if self.options.strict_truthiness => Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("Expected a boolean value but got {}", other.describe()),
+            )),
+            Literal::Nil => Ok(false),
+            _ => Ok(true),
+        }
+    }
+}
+
+fn import_error(message: String) -> RuntimeError {
+    RuntimeError::new(ErrorType::ImportError, message)
+}
+
+// a module that doesn't parse isn't run at all
+fn parse_module(source: &str, script: &str) -> Result<Vec<Stmt>, RuntimeError> {
+    let mut parser = Parser::new(Scanner::new(source));
+    let stmts = parser.parse();
+
+    let mut errors: Vec<String> = parser
+        .scan_errors()
+        .into_iter()
+        .map(|err| format!("{}:{}: {}", script, err.line, err.message))
+        .collect();
+    let stmts = stmts
+        .into_iter()
+        .filter_map(|stmt| {
+            stmt.map_err(|err| errors.push(format!("{}:{}: {}", script, err.line, err.message)))
+                .ok()
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(stmts),
+        false => Err(import_error(format!(
+            "Couldn't parse {}\n{}",
+            script,
+            errors.join("\n")
+        ))),
+    }
+}
@@ -0,0 +1,86 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    callable::NativeFn,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    interpreter::Interpreter,
+    natives,
+};
+
+// shared, so every copy of a list value sees pushes/assignments made through the others
+pub type List = Rc<RefCell<Vec<Literal>>>;
+
+pub fn new_list(items: Vec<Literal>) -> Literal {
+    Literal::List(Rc::new(RefCell::new(items)))
+}
+
+pub fn method(name: &str) -> Option<NativeFn> {
+    match name {
+        "push" => Some(NativeFn::new("push", 1, push)),
+        "pop" => Some(NativeFn::new("pop", 0, pop)),
+        "len" => Some(NativeFn::new("len", 0, len)),
+        "insert" => Some(NativeFn::new("insert", 2, insert)),
+        "remove" => Some(NativeFn::new("remove", 1, remove)),
+        "slice" => Some(NativeFn::new("slice", 2, slice)),
+        _ => None,
+    }
+}
+
+fn push(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    natives::receiver(&args, Literal::as_list)
+        .borrow_mut()
+        .push(args[1].clone());
+    Ok(Literal::Nil)
+}
+
+fn pop(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    natives::receiver(&args, Literal::as_list)
+        .borrow_mut()
+        .pop()
+        .ok_or_else(|| {
+            RuntimeError::new(
+                ErrorType::IndexError,
+                "Can't pop from an empty list".to_owned(),
+            )
+        })
+}
+
+fn len(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::Integer(
+        natives::receiver(&args, Literal::as_list).borrow().len() as i64,
+    ))
+}
+
+fn insert(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let list = natives::receiver(&args, Literal::as_list);
+    let len = list.borrow().len();
+    // inserting right after the last element is allowed, same as Vec::insert
+    let at = natives::position("list", len, &args[1])?;
+    list.borrow_mut().insert(at, args[2].clone());
+    Ok(Literal::Nil)
+}
+
+fn remove(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let list = natives::receiver(&args, Literal::as_list);
+    let len = list.borrow().len();
+    let at = natives::index("list", len, &args[1])?;
+    let removed = list.borrow_mut().remove(at);
+    Ok(removed)
+}
+
+// copies start..end into a new list, end is exclusive
+fn slice(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let list = natives::receiver(&args, Literal::as_list);
+    let len = list.borrow().len();
+    let start = natives::position("list", len, &args[1])?;
+    let end = natives::position("list", len, &args[2])?;
+    if start > end {
+        return Err(RuntimeError::new(
+            ErrorType::IndexError,
+            format!("Slice start {} is greater than slice end {}", start, end),
+        ));
+    }
+    let items = list.borrow()[start..end].to_vec();
+    Ok(new_list(items))
+}
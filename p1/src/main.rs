@@ -1,22 +1,43 @@
 use std::{
     env, fs,
     io::{self, stdout, Write},
+    process, thread,
 };
 
+use interpreter::{DivisionByZero, Interpreter, Options};
 use parser::Parser;
 use scanner::Scanner;
 
+mod callable;
 mod environment;
+mod error;
 mod expr;
+mod files;
+mod interpreter;
+mod list;
+mod map;
+mod math;
+mod module;
+mod natives;
 mod parser;
 mod scanner;
 mod stmt;
+mod string;
 mod tokens;
 
 struct Lox {
     has_error: bool,
+    had_runtime_error: bool,
+    exit_code: Option<i32>, // set once the script calls exit()
+    interpreter: Interpreter,
 }
 
+// the exit codes from sysexits.h, which other Lox implementations use too
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+
 struct LoxError {
     line: u32,
     loc_in_line: u32,
@@ -25,25 +46,67 @@ struct LoxError {
 }
 
 impl Lox {
-    fn init(args: Vec<String>) {
-        let mut lox = Lox { has_error: false };
+    // returns the process's exit code
+    fn init(args: Vec<String>) -> i32 {
+        let mut args = args.into_iter().skip(1).peekable();
+
+        // flags only count before the script, everything after it is the script's own
+        let mut options = Options::default();
+        while let Some(flag) = args.next_if(|arg| arg.starts_with("--")) {
+            match flag.as_str() {
+                "--strict-truthiness" => options.strict_truthiness = true,
+                "--strict-division" => options.division_by_zero = DivisionByZero::Error,
+                "--strict-concatenation" => options.strict_concatenation = true,
+                _ => match flag.strip_prefix("--module-path=") {
+                    Some(dir) => options.module_path.push(dir.into()),
+                    None => return Lox::usage(),
+                },
+            }
+        }
 
-        match args.len() {
-            1 => lox.run_prompt(),
-            2 => lox.run_file(&args[1]),
-            _ => eprintln!("Usage: rlox <file>"),
+        let file = args.next();
+        let script = file.as_deref().unwrap_or("<stdin>");
+        let mut lox = Lox {
+            has_error: false,
+            had_runtime_error: false,
+            exit_code: None,
+            interpreter: Interpreter::new(options, script.into(), args.collect()),
         };
+
+        match file {
+            Some(file) => lox.run_file(&file),
+            None => lox.run_prompt(),
+        }
     }
 
-    fn run_file(&mut self, file_name: &str) {
-        let file = fs::read_to_string(file_name).unwrap();
-        let mut parser = Parser::new();
-        self.run(&file, &mut parser);
+    fn usage() -> i32 {
+        eprintln!(
+            "Usage: rlox [--strict-truthiness] [--strict-division] [--strict-concatenation] \
+             [--module-path=DIR]... [file [args...]]"
+        );
+        EX_USAGE
+    }
+
+    fn run_file(&mut self, file_name: &str) -> i32 {
+        match fs::read_to_string(file_name) {
+            Ok(file) => self.run(&file),
+            Err(err) => {
+                eprintln!("Couldn't read {}: {}", file_name, err);
+                return EX_NOINPUT;
+            }
+        }
+
+        match self.exit_code {
+            Some(code) => code,
+            None if self.has_error => EX_DATAERR,
+            None if self.had_runtime_error => EX_SOFTWARE,
+            None => 0,
+        }
     }
 
-    fn run_prompt(&mut self) {
+    // errors don't end the prompt, so only exit() changes its exit code
+    fn run_prompt(&mut self) -> i32 {
         let mut input = String::new();
-        let mut parser = Parser::new();
         loop {
             if self.has_error {
                 self.has_error = false;
@@ -52,45 +115,49 @@ impl Lox {
             let _ = stdout().flush();
 
             input.clear();
-            io::stdin().read_line(&mut input).unwrap();
-            if input.trim_end() == "exit" {
-                return;
+            // 0 bytes read means stdin was closed
+            if io::stdin().read_line(&mut input).unwrap() == 0 || input.trim_end() == "exit" {
+                return 0;
             }
 
-            self.run(&input, &mut parser);
+            self.run(&input);
+            if let Some(code) = self.exit_code {
+                return code;
+            }
         }
     }
 
-    fn run(&mut self, source: &str, parser: &mut Parser) {
-        let mut scanner = Scanner::new(source.to_owned(), self);
-        scanner.scan_tokens();
-        let tokens = scanner.tokens();
-        parser.reset_tokens(tokens);
-
-        parser
-            .parse()
-            .into_iter()
-            .filter_map(|stmt| match stmt {
-                Ok(s) => Some(s.interpret_stmt()),
+    fn run(&mut self, source: &str) {
+        let mut parser = Parser::new(Scanner::new(source));
+        let stmts = parser.parse();
+        for err in parser.scan_errors() {
+            self.report(err.line, err.loc_in_line, &err.chars_in_line, &err.message);
+        }
+
+        let mut had_runtime_error = false;
+        for stmt in stmts {
+            match stmt {
+                Ok(s) => match s.interpret_stmt(&mut self.interpreter) {
+                    Err(err) if err.exit_code().is_some() => {
+                        self.exit_code = err.exit_code();
+                        return;
+                    }
+                    Err(err) => {
+                        eprintln!("{}", self.interpreter.traced(err));
+                        had_runtime_error = true;
+                    }
+                    Ok(_) => (),
+                },
                 Err(err) => {
-                    self.report(err.line, err.loc_in_line, &err.chars_in_line, &err.message);
-                    None
-                }
-            })
-            .fold(Ok(()), |acc, el| match (acc, el) {
-                (Err(s), Err(discard)) => {
-                    eprintln!("{discard}");
-                    Err(s)
+                    self.report(err.line, err.loc_in_line, &err.chars_in_line, &err.message)
                 }
-                (Ok(_), Err(disc)) => {
-                    eprintln!("{disc}");
-                    Err("Something went wrong, check error messages")
-                }
-                (Err(s), Ok(_)) => Err(s),
-                (Ok(_), Ok(_)) => Ok(()),
-            })
-            .err()
-            .map(|err| eprintln!("{err}"));
+            }
+        }
+
+        if had_runtime_error {
+            eprintln!("Something went wrong, check error messages");
+            self.had_runtime_error = true;
+        }
     }
 
     fn report(&mut self, line: u32, loc_in_line: u32, chars_in_line: &str, message: &str) {
@@ -114,5 +181,11 @@ impl LoxError {
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    Lox::init(args);
+    let code = thread::Builder::new()
+        .stack_size(interpreter::STACK_SIZE)
+        .spawn(|| Lox::init(args))
+        .expect("couldn't start the interpreter thread")
+        .join()
+        .unwrap();
+    process::exit(code);
 }
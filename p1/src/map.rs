@@ -0,0 +1,176 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    callable::NativeFn,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    interpreter::Interpreter,
+    list, natives,
+};
+
+// shared like lists, every copy of a map value sees the same entries
+pub type Map = Rc<RefCell<OrderedMap>>;
+
+pub fn new_map(map: OrderedMap) -> Literal {
+    Literal::Map(Rc::new(RefCell::new(map)))
+}
+
+// only plain values can be keys. Whole floats share a key with the equal integer (which also
+// folds -0 into 0), any other float is hashed by its bits
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Integer(i64),
+    Number(u64),
+    String(String),
+    Boolean(bool),
+    Nil,
+}
+
+impl TryFrom<&Literal> for Key {
+    type Error = RuntimeError;
+
+    fn try_from(value: &Literal) -> Result<Self, Self::Error> {
+        match value {
+            Literal::Integer(i) => Ok(Key::Integer(*i)),
+            Literal::Number(n) if n.is_nan() => Err(RuntimeError::new(
+                ErrorType::TypeError,
+                "NaN can't be used as a map key".to_owned(),
+            )),
+            Literal::Number(n)
+                if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 =>
+            {
+                Ok(Key::Integer(*n as i64))
+            }
+            Literal::Number(n) => Ok(Key::Number(n.to_bits())),
+            Literal::String(s) => Ok(Key::String(s.clone())),
+            Literal::Boolean(b) => Ok(Key::Boolean(*b)),
+            Literal::Nil => Ok(Key::Nil),
+            other => Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("{} can't be used as a map key", other),
+            )),
+        }
+    }
+}
+
+impl From<&Key> for Literal {
+    fn from(key: &Key) -> Self {
+        match key {
+            Key::Integer(i) => Literal::Integer(*i),
+            Key::Number(bits) => Literal::Number(f64::from_bits(*bits)),
+            Key::String(s) => Literal::String(s.clone()),
+            Key::Boolean(b) => Literal::Boolean(*b),
+            Key::Nil => Literal::Nil,
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::String(s) => write!(f, "\"{}\"", s),
+            other => write!(f, "{}", Literal::from(other)),
+        }
+    }
+}
+
+// iterates in insertion order, overwriting a key keeps its original position
+#[derive(Debug, Default)]
+pub struct OrderedMap {
+    entries: Vec<(Key, Literal)>,
+    positions: HashMap<Key, usize>, // index of each key in entries
+}
+
+impl OrderedMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Literal> {
+        self.positions.get(key).map(|&at| &self.entries[at].1)
+    }
+
+    pub fn insert(&mut self, key: Key, val: Literal) {
+        match self.positions.get(&key) {
+            Some(&at) => self.entries[at].1 = val,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, val));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<Literal> {
+        let at = self.positions.remove(key)?;
+        let (_, val) = self.entries.remove(at);
+        // everything after the removed entry shifted down by one
+        for (key, _) in &self.entries[at..] {
+            if let Some(position) = self.positions.get_mut(key) {
+                *position -= 1;
+            }
+        }
+        Some(val)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Key, Literal)> {
+        self.entries.iter()
+    }
+}
+
+pub fn method(name: &str) -> Option<NativeFn> {
+    match name {
+        "keys" => Some(NativeFn::new("keys", 0, keys)),
+        "values" => Some(NativeFn::new("values", 0, values)),
+        "has" => Some(NativeFn::new("has", 1, has)),
+        "delete" => Some(NativeFn::new("delete", 1, delete)),
+        "len" => Some(NativeFn::new("len", 0, len)),
+        _ => None,
+    }
+}
+
+fn keys(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let keys = natives::receiver(&args, Literal::as_map)
+        .borrow()
+        .iter()
+        .map(|(key, _)| Literal::from(key))
+        .collect();
+    Ok(list::new_list(keys))
+}
+
+fn values(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let values = natives::receiver(&args, Literal::as_map)
+        .borrow()
+        .iter()
+        .map(|(_, val)| val.clone())
+        .collect();
+    Ok(list::new_list(values))
+}
+
+fn has(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let key = Key::try_from(&args[1])?;
+    Ok(Literal::Boolean(
+        natives::receiver(&args, Literal::as_map)
+            .borrow()
+            .get(&key)
+            .is_some(),
+    ))
+}
+
+// returns the removed value, or nil if the key wasn't there
+fn delete(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let key = Key::try_from(&args[1])?;
+    Ok(natives::receiver(&args, Literal::as_map)
+        .borrow_mut()
+        .remove(&key)
+        .unwrap_or(Literal::Nil))
+}
+
+fn len(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::Integer(
+        natives::receiver(&args, Literal::as_map).borrow().len() as i64,
+    ))
+}
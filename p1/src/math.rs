@@ -0,0 +1,201 @@
+use std::{
+    f64::consts,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    callable::{Callable, NativeFn},
+    environment::Environment,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    interpreter::Interpreter,
+};
+
+// what `import "math";` binds, every function takes and returns floats (integers are converted)
+pub fn environment() -> Environment {
+    let functions = [
+        NativeFn::new("sqrt", 1, sqrt),
+        NativeFn::new("pow", 2, pow),
+        NativeFn::new("abs", 1, abs),
+        NativeFn::new("floor", 1, floor),
+        NativeFn::new("ceil", 1, ceil),
+        NativeFn::new("round", 1, round),
+        NativeFn::new("min", 2, min),
+        NativeFn::new("max", 2, max),
+        NativeFn::new("sin", 1, sin),
+        NativeFn::new("cos", 1, cos),
+        NativeFn::new("tan", 1, tan),
+        NativeFn::new("atan2", 2, atan2),
+        NativeFn::new("log", 1, log),
+        NativeFn::new("exp", 1, exp),
+        NativeFn::new("random", 0, random),
+        NativeFn::new("seed", 1, seed),
+    ];
+
+    let mut environment = Environment::new();
+    for function in functions {
+        environment
+            .define(
+                function.name().to_owned(),
+                Literal::Callable(Callable::Native(function)),
+            )
+            .expect("the functions come before the constants");
+    }
+    environment
+        .define_const("PI".to_owned(), Literal::Number(consts::PI))
+        .and_then(|_| environment.define_const("E".to_owned(), Literal::Number(consts::E)))
+        .expect("each constant is defined once");
+    environment
+}
+
+fn number(function: &str, arg: &Literal) -> Result<f64, RuntimeError> {
+    match arg {
+        Literal::Integer(i) => Ok(*i as f64),
+        Literal::Number(n) => Ok(*n),
+        other => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!(
+                "{} expected a number but got {}",
+                function,
+                other.describe()
+            ),
+        )),
+    }
+}
+
+fn unary(function: &str, args: &[Literal], f: fn(f64) -> f64) -> Result<Literal, RuntimeError> {
+    Ok(Literal::Number(f(number(function, &args[0])?)))
+}
+
+fn binary(
+    function: &str,
+    args: &[Literal],
+    f: fn(f64, f64) -> f64,
+) -> Result<Literal, RuntimeError> {
+    let l = number(function, &args[0])?;
+    let r = number(function, &args[1])?;
+    Ok(Literal::Number(f(l, r)))
+}
+
+fn sqrt(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("sqrt", &args, f64::sqrt)
+}
+
+fn pow(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    binary("pow", &args, f64::powf)
+}
+
+fn abs(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("abs", &args, f64::abs)
+}
+
+fn floor(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("floor", &args, f64::floor)
+}
+
+fn ceil(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("ceil", &args, f64::ceil)
+}
+
+// halfway cases round away from zero
+fn round(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("round", &args, f64::round)
+}
+
+// nan if either side is nan, rather than f64::min/max quietly picking the other one
+fn min(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    binary("min", &args, |l, r| {
+        if l.is_nan() || r.is_nan() {
+            f64::NAN
+        } else {
+            l.min(r)
+        }
+    })
+}
+
+fn max(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    binary("max", &args, |l, r| {
+        if l.is_nan() || r.is_nan() {
+            f64::NAN
+        } else {
+            l.max(r)
+        }
+    })
+}
+
+fn sin(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("sin", &args, f64::sin)
+}
+
+fn cos(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("cos", &args, f64::cos)
+}
+
+fn tan(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("tan", &args, f64::tan)
+}
+
+// atan2(y, x), in the same order as the arguments of f64::atan2
+fn atan2(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    binary("atan2", &args, f64::atan2)
+}
+
+// natural logarithm
+fn log(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("log", &args, f64::ln)
+}
+
+fn exp(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    unary("exp", &args, f64::exp)
+}
+
+// a float in [0, 1)
+fn random(interpreter: &mut Interpreter, _: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::Number(interpreter.random().next_f64()))
+}
+
+// the same seed gives the same sequence from random() afterwards
+fn seed(interpreter: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    match &args[0] {
+        Literal::Integer(i) => {
+            *interpreter.random() = Random::new(*i as u64);
+            Ok(Literal::Nil)
+        }
+        other => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!("seed expected an integer but got {}", other.describe()),
+        )),
+    }
+}
+
+// splitmix64, good enough for scripts and needs no dependencies
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // seeded from the clock, so each run differs until seed() is called
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // the top 53 bits, which is all an f64 in [0, 1) can hold
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,37 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{environment::Environment, expr::Literal};
+
+// an imported file, its top-level definitions are read with `module.name`
+pub struct Module {
+    name: String,
+    environment: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    pub fn new(name: String, environment: Rc<RefCell<Environment>>) -> Self {
+        Self { name, environment }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // only what the file itself defined, not the globals around it
+    pub fn get(&self, key: &str) -> Option<Literal> {
+        self.environment.borrow().get_own(key)
+    }
+}
+
+// the module's functions close over its environment, so only the name is shown
+impl fmt::Debug for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Module({})", self.name)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
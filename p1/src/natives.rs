@@ -0,0 +1,122 @@
+use std::env;
+
+use crate::{
+    callable::NativeFn,
+    environment::Environment,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    files,
+    interpreter::Interpreter,
+    math,
+};
+
+// functions defined in the global scope of every interpreter
+pub fn globals() -> Vec<NativeFn> {
+    let mut natives = vec![
+        NativeFn::new("len", 1, len),
+        NativeFn::new("getenv", 1, getenv),
+        NativeFn::new("exit", 1, exit),
+    ];
+    natives.extend(files::natives());
+    natives
+}
+
+// modules that come with the interpreter, `import "math";` gets these before looking for a file
+pub fn builtin_module(name: &str) -> Option<Environment> {
+    match name {
+        "math" => Some(math::environment()),
+        _ => None,
+    }
+}
+
+// the value a method was called on, methods are only ever bound to values of the type they
+// were looked up on, so the receiver is always there
+pub fn receiver<'a, T: ?Sized>(
+    args: &'a [Literal],
+    as_type: fn(&'a Literal) -> Option<&'a T>,
+) -> &'a T {
+    as_type(&args[0]).expect("method bound to a value of another type")
+}
+
+pub fn string_arg<'a>(function: &str, arg: &'a Literal) -> Result<&'a str, RuntimeError> {
+    arg.as_str().ok_or_else(|| {
+        RuntimeError::new(
+            ErrorType::TypeError,
+            format!("{} expected a string but got {}", function, arg.describe()),
+        )
+    })
+}
+
+// only whole numbers in 0..len are valid indices, floats are fine as long as they're whole.
+// `what` is the kind of value indexed into. Any bad index is an IndexError, from `xs[i]` and
+// the methods alike
+pub fn index(what: &str, len: usize, at: &Literal) -> Result<usize, RuntimeError> {
+    bounded_index(what, len, at, len)
+}
+
+// an index that can also be one past the end, for where to insert or where a slice ends
+pub fn position(what: &str, len: usize, at: &Literal) -> Result<usize, RuntimeError> {
+    bounded_index(what, len, at, len + 1)
+}
+
+// valid indices are below `end`
+fn bounded_index(what: &str, len: usize, at: &Literal, end: usize) -> Result<usize, RuntimeError> {
+    let index_error = |message| RuntimeError::new(ErrorType::IndexError, message);
+    let index = match at {
+        Literal::Integer(i) => *i as f64,
+        Literal::Number(n) if n.fract() != 0.0 => {
+            return Err(index_error(format!(
+                "Index must be a whole number but got {}",
+                n
+            )))
+        }
+        Literal::Number(n) => *n,
+        other => {
+            return Err(index_error(format!(
+                "Index must be a number but got {}",
+                other.describe()
+            )))
+        }
+    };
+    if index < 0.0 || index >= end as f64 {
+        return Err(index_error(format!(
+            "Index {} out of bounds for {} of length {}",
+            at, what, len
+        )));
+    }
+    Ok(index as usize)
+}
+
+fn len(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    match &args[0] {
+        Literal::List(list) => Ok(Literal::Integer(list.borrow().len() as i64)),
+        Literal::Map(map) => Ok(Literal::Integer(map.borrow().len() as i64)),
+        Literal::String(s) => Ok(Literal::Integer(s.chars().count() as i64)),
+        other => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!("Can't take the length of {}", other.describe()),
+        )),
+    }
+}
+
+// nil if it isn't set, or isn't valid unicode
+fn getenv(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let name = string_arg("getenv", &args[0])?;
+    Ok(env::var(name).map_or(Literal::Nil, Literal::String))
+}
+
+// unwinds the whole script, running any `finally` blocks on the way, and ends the process
+// with `code`
+fn exit(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    match &args[0] {
+        Literal::Integer(code) if (0..=255).contains(code) => Err(RuntimeError::exit(*code as i32)),
+        Literal::Integer(code) => Err(RuntimeError::new(
+            ErrorType::ValueError,
+            format!("Exit code {} must be between 0 and 255", code),
+        )),
+        other => Err(RuntimeError::new(
+            ErrorType::TypeError,
+            format!("exit expected an integer but got {}", other.describe()),
+        )),
+    }
+}
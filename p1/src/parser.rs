@@ -1,37 +1,79 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
 
 use crate::{
-    environment::Environment,
-    expr::{Assign, Binary, BinaryOp, Expr, Grouping, Literal, Unary, UnaryOp},
-    stmt::{Stmt, Var},
+    expr::{
+        Assign, Binary, BinaryOp, Call, Comparison, Conditional, Expr, Get, Grouping, Index,
+        IndexAssign, ListLiteral, Literal, Logical, LogicalOp, MapLiteral, Unary, UnaryOp, Update,
+    },
+    scanner::{ScanError, Scanner},
+    stmt::{Catch, Function, If, Import, Param, Return, Stmt, Throw, Try, Var, While},
     tokens::{self, Token, TokenType},
     LoxError,
 };
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: u32,
+// pulls tokens from the scanner one at a time instead of holding the whole file's worth
+pub struct Parser<'a> {
+    tokens: Scanner<'a>,
+    scan_errors: Vec<ScanError>,
+    previous: Token,
+    current: Token,
+    lookahead: VecDeque<Token>, // already pulled past current, only filled by peek_ahead()
+    // names declared so far in each enclosing block, true for constants, so assigning to a
+    // constant can be caught before running (names it can't see are checked at runtime instead)
+    scopes: Vec<HashMap<Rc<str>, bool>>,
+    loop_depth: usize,     // break and continue are only allowed inside a loop body
+    function_depth: usize, // and return inside a function body
+    // mistakes in code that parses fine, like assigning to a constant. Recorded instead of
+    // returned, the parser isn't lost so there's nothing to synchronize past
+    errors: Vec<LoxError>,
 }
 
-impl Parser {
-    pub fn new() -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(mut tokens: Scanner<'a>) -> Self {
+        let mut scan_errors = vec![];
+        let current = Self::next_token(&mut tokens, &mut scan_errors);
         Self {
-            tokens: vec![],
-            current: 0,
+            tokens,
+            scan_errors,
+            previous: current.clone(), // never read before the first advance()
+            current,
+            lookahead: VecDeque::new(),
+            scopes: vec![HashMap::new()],
+            loop_depth: 0,
+            function_depth: 0,
+            errors: vec![],
+        }
+    }
+
+    // scan errors are set aside so parsing carries on with the tokens around them
+    fn next_token(tokens: &mut Scanner<'a>, scan_errors: &mut Vec<ScanError>) -> Token {
+        loop {
+            match tokens.next() {
+                Some(Ok(token)) => return token,
+                Some(Err(err)) => scan_errors.push(err),
+                None => unreachable!("parser never advances past Eof"),
+            }
         }
     }
 
-    pub fn reset_tokens(&mut self, tokens: Vec<Token>) {
-        self.tokens = tokens;
-        self.current = 0;
+    // consumes, should only be called once parse() is done pulling tokens
+    pub fn scan_errors(self) -> Vec<ScanError> {
+        self.scan_errors
     }
 
     pub fn parse(&mut self) -> Vec<Result<Stmt, LoxError>> {
         let mut stmts = vec![];
-        let top_level_env = Rc::new(RefCell::new(Environment::new()));
         while !self.is_at_end() {
-            match self.declaration(top_level_env.clone()) {
-                Ok(stmt) => stmts.push(Ok(stmt)),
+            let stmt = self.declaration();
+            // a declaration with recorded errors isn't run, they're reported in its place
+            let recorded = !self.errors.is_empty();
+            stmts.extend(self.errors.drain(..).map(Err));
+            match stmt {
+                Ok(stmt) if !recorded => stmts.push(Ok(stmt)),
+                Ok(_) => (),
                 Err(e) => {
                     stmts.push(Err(e));
                     self.synchronize();
@@ -41,115 +83,648 @@ impl Parser {
         stmts
     }
 
-    fn declaration(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Stmt, LoxError> {
-        if self.adv_if_match(&[TokenType::Var]) {
-            self.var_declaration(environment)
+    fn declaration(&mut self) -> Result<Stmt, LoxError> {
+        if self.adv_if_match(&[TokenType::Var, TokenType::Const]) {
+            self.var_declaration()
+        } else if self.adv_if_match(&[TokenType::Import]) {
+            self.import_declaration()
+        } else if self.check(&TokenType::Fun)
+            && self.peek_ahead(1).token_type == TokenType::Identifier
+        {
+            self.advance();
+            self.function()
         } else {
-            self.statement(environment)
+            self.statement()
         }
     }
 
-    fn var_declaration(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Stmt, LoxError> {
+    fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let constant = self.previous().token_type == TokenType::Const;
         let name = self
             .try_consume(TokenType::Identifier, "Expect variable name")?
             .clone();
+        // checked before the rest is parsed, so the error is reported at the name
+        self.check_redeclarable(&name);
 
-        let initializer = self
-            .adv_if_match(&[TokenType::Equal])
-            .then(|| self.expression(environment.clone()));
+        let initializer = match self.adv_if_match(&[TokenType::Equal]) {
+            true => Some(self.expression()?),
+            false if constant => {
+                return Err(LoxError::new(
+                    name.line,
+                    0,
+                    name.lexeme.to_string(),
+                    "Expect '=' after constant name".to_owned(),
+                ))
+            }
+            false => None,
+        };
 
         self.try_consume(
             TokenType::Semicolon,
             "Expect ';' after variable declaration",
         )?;
+
+        // declared after the initializer, `var x = x;` still refers to an outer x
+        self.scope().insert(name.lexeme.clone(), constant);
+
         match initializer {
-            Some(val) => Ok(Stmt::Var(Var::with_init(name, val?), environment.clone())),
-            None => Ok(Stmt::Var(Var::new(name), environment.clone())),
+            Some(val) if constant => Ok(Stmt::Var(Var::constant(name, val))),
+            Some(val) => Ok(Stmt::Var(Var::with_init(name, val))),
+            None => Ok(Stmt::Var(Var::new(name))),
+        }
+    }
+
+    // import STRING ( as IDENTIFIER )? ;
+    fn import_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let line = self.previous().line;
+        let path = self.try_consume(TokenType::String, "Expect module path after 'import'")?;
+        let path = match &path.literal {
+            Some(tokens::Literal::String(path)) => path.to_string(),
+            _ => unreachable!("string tokens always have a string literal"),
+        };
+
+        let alias = match self.adv_if_match(&[TokenType::As]) {
+            true => Some(
+                self.try_consume(TokenType::Identifier, "Expect module name after 'as'")?
+                    .clone(),
+            ),
+            false => None,
+        };
+        self.try_consume(TokenType::Semicolon, "Expect ';' after import")?;
+
+        if let Some(alias) = &alias {
+            self.declare(alias, false);
+        }
+        Ok(Stmt::Import(Import::new(path, alias, line)))
+    }
+
+    // fun IDENTIFIER ( parameters? ) block
+    fn function(&mut self) -> Result<Stmt, LoxError> {
+        let name = self
+            .try_consume(TokenType::Identifier, "Expect function name")?
+            .clone();
+        // declared before the body so the function can call itself
+        self.declare(&name, false);
+
+        self.try_consume(TokenType::LeftParen, "Expect '(' after function name")?;
+        let function = self.function_rest(Some(name))?;
+        Ok(Stmt::Function(Rc::new(function)))
+    }
+
+    // parameters? ) block, everything after a function's opening parenthesis
+    fn function_rest(&mut self, name: Option<Token>) -> Result<Function, LoxError> {
+        let (params, rest) = self.parameters()?;
+        self.try_consume(TokenType::LeftBrace, "Expect '{' before function body")?;
+        let body = self.function_body(&params, &rest, |parser| parser.block())?;
+        Ok(Function::new(name, params, rest, body))
+    }
+
+    // param ( , param ) * ( , ... IDENTIFIER )? ), with param being IDENTIFIER ( = assignment )?
+    // the opening parenthesis is already consumed
+    fn parameters(&mut self) -> Result<(Vec<Param>, Option<Token>), LoxError> {
+        let mut params: Vec<Param> = vec![];
+        let mut rest = None;
+        while !self.check(&TokenType::RightParen) {
+            if self.adv_if_match(&[TokenType::DotDotDot]) {
+                rest = Some(
+                    self.try_consume(TokenType::Identifier, "Expect parameter name after '...'")?
+                        .clone(),
+                );
+                break;
+            }
+
+            let name = self
+                .try_consume(TokenType::Identifier, "Expect parameter name")?
+                .clone();
+            let default = match self.adv_if_match(&[TokenType::Equal]) {
+                true => Some(self.assignment()?),
+                false if params.last().is_some_and(|param| param.default.is_some()) => {
+                    return Err(LoxError::new(
+                        name.line,
+                        0,
+                        name.lexeme.to_string(),
+                        "Parameters after one with a default value need a default too".to_owned(),
+                    ))
+                }
+                false => None,
+            };
+            params.push(Param::new(name, default));
+
+            if !self.adv_if_match(&[TokenType::Comma]) {
+                break;
+            }
         }
+        self.try_consume(
+            TokenType::RightParen,
+            match rest {
+                Some(_) => "Expect ')' after rest parameter, it has to be the last one",
+                None => "Expect ')' after parameters",
+            },
+        )?;
+        Ok((params, rest))
     }
 
-    fn statement(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Stmt, LoxError> {
+    // parses the body with the parameters in scope, loops around the function don't reach into it
+    fn function_body<T>(
+        &mut self,
+        params: &[Param],
+        rest: &Option<Token>,
+        body: impl FnOnce(&mut Self) -> Result<T, LoxError>,
+    ) -> Result<T, LoxError> {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.function_depth += 1;
+        self.scopes.push(
+            params
+                .iter()
+                .map(|param| &param.name)
+                .chain(rest)
+                .map(|name| (name.lexeme.clone(), false))
+                .collect(),
+        );
+        let body = body(self);
+        self.scopes.pop();
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    // whether the parenthesis is followed by `=>` once it's closed, checked without consuming
+    // anything since until the arrow the parameters look just like a parenthesized expression.
+    // Only `()`, `(name ,`/`=`/`)` and `(...` can start parameters, anything else is a grouping
+    // and is told apart right away instead of scanning to the closing parenthesis
+    fn at_arrow_function(&mut self) -> bool {
+        match self.peek_ahead(1).token_type {
+            TokenType::RightParen | TokenType::DotDotDot => (),
+            TokenType::Identifier => match self.peek_ahead(2).token_type {
+                TokenType::Comma | TokenType::Equal | TokenType::RightParen => (),
+                _ => return false,
+            },
+            _ => return false,
+        }
+
+        let mut depth = 0;
+        let mut n = 1;
+        loop {
+            match self.peek_ahead(n).token_type {
+                TokenType::RightParen if depth == 0 => break,
+                TokenType::LeftParen | TokenType::LeftBracket | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBracket | TokenType::RightBrace => {
+                    depth -= 1
+                }
+                TokenType::Eof => return false,
+                _ => (),
+            }
+            n += 1;
+        }
+        self.peek_ahead(n + 1).token_type == TokenType::Arrow
+    }
+
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
         if self.adv_if_match(&[TokenType::Print]) {
-            self.print_statement(environment)
-        } else if self.adv_if_match(&[TokenType::LeftBrace]) {
-            Ok(Stmt::Block(self.block(environment)?))
+            self.print_statement()
+        } else if self.adv_if_match(&[TokenType::If]) {
+            self.if_statement()
+        } else if self.adv_if_match(&[TokenType::While]) {
+            self.while_statement()
+        } else if self.adv_if_match(&[TokenType::For]) {
+            self.for_statement()
+        } else if self.adv_if_match(&[TokenType::Return]) {
+            self.return_statement()
+        } else if self.adv_if_match(&[TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.adv_if_match(&[TokenType::Try]) {
+            self.try_statement()
+        } else if self.adv_if_match(&[TokenType::Break, TokenType::Continue]) {
+            self.loop_jump_statement()
+        } else if self.check(&TokenType::LeftBrace) && !self.at_map_literal() {
+            self.advance();
+            Ok(Stmt::Block(self.block()?))
         } else {
-            self.expr_statement(environment)
+            self.expr_statement()
         }
     }
 
-    fn print_statement(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Stmt, LoxError> {
-        let expr = self.expression(environment)?;
+    // `{ key: ...` can't start a block, so a statement starting like that is a map literal
+    fn at_map_literal(&mut self) -> bool {
+        self.peek_ahead(2).token_type == TokenType::Colon
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let expr = self.expression()?;
         self.try_consume(TokenType::Semicolon, "Expected semicolon after expression")?;
         Ok(Stmt::Print(expr))
     }
 
-    fn block(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Vec<Stmt>, LoxError> {
-        let mut stmts = vec![];
-        let new_env = Rc::new(RefCell::new(Environment::with_enclosing(
-            environment.clone(),
+    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.try_consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
+        let line = self.peek().line;
+        let condition = self.expression()?;
+        self.try_consume(TokenType::RightParen, "Expected ')' after if condition")?;
+
+        let then_branch = self.statement()?;
+        let else_branch = match self.adv_if_match(&[TokenType::Else]) {
+            true => Some(self.statement()?),
+            false => None,
+        };
+
+        Ok(Stmt::If(If::new(condition, line, then_branch, else_branch)))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.try_consume(TokenType::LeftParen, "Expected '(' after 'while'")?;
+        let line = self.peek().line;
+        let condition = self.expression()?;
+        self.try_consume(TokenType::RightParen, "Expected ')' after while condition")?;
+
+        let body = self.loop_body()?;
+        Ok(Stmt::While(While::new(condition, line, body)))
+    }
+
+    // for ( var declaration | expression statement | ; ) expression? ; expression? ) statement
+    // becomes a block holding the initializer and a while loop that keeps the increment apart
+    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        self.try_consume(TokenType::LeftParen, "Expected '(' after 'for'")?;
+
+        // the loop variable lives in the block around the loop
+        self.scopes.push(HashMap::new());
+        let result = self.for_clauses();
+        self.scopes.pop();
+        result
+    }
+
+    fn for_clauses(&mut self) -> Result<Stmt, LoxError> {
+        let initializer = if self.adv_if_match(&[TokenType::Semicolon]) {
+            None
+        } else if self.adv_if_match(&[TokenType::Var, TokenType::Const]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expr_statement()?)
+        };
+
+        let line = self.peek().line;
+        let condition = match self.check(&TokenType::Semicolon) {
+            true => Expr::Literal(Literal::Boolean(true)),
+            false => self.expression()?,
+        };
+        self.try_consume(TokenType::Semicolon, "Expected ';' after loop condition")?;
+
+        let increment = match self.check(&TokenType::RightParen) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+        self.try_consume(TokenType::RightParen, "Expected ')' after for clauses")?;
+
+        let body = self.loop_body()?;
+        let mut stmts: Vec<Stmt> = initializer.into_iter().collect();
+        stmts.push(Stmt::While(While::with_increment(
+            condition, line, body, increment,
         )));
+        Ok(Stmt::Block(stmts))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        if self.function_depth == 0 {
+            self.errors.push(LoxError::new(
+                keyword.line,
+                0,
+                keyword.lexeme.to_string(),
+                "Can't return from top-level code".to_owned(),
+            ));
+        }
+
+        let value = match self.check(&TokenType::Semicolon) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+        self.try_consume(
+            TokenType::Semicolon,
+            "Expected semicolon after return value",
+        )?;
+        Ok(Stmt::Return(Return::new(value)))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, LoxError> {
+        let line = self.previous().line;
+        let expr = self.expression()?;
+        self.try_consume(
+            TokenType::Semicolon,
+            "Expected semicolon after thrown value",
+        )?;
+        Ok(Stmt::Throw(Throw::new(expr, line)))
+    }
+
+    // try block ( catch ( IDENTIFIER ) block )? ( finally block )?, with at least one of the two
+    fn try_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        self.try_consume(TokenType::LeftBrace, "Expected '{' after 'try'")?;
+        let body = self.block()?;
+
+        let catch = match self.adv_if_match(&[TokenType::Catch]) {
+            true => Some(self.catch_clause()?),
+            false => None,
+        };
+        let finally = match self.adv_if_match(&[TokenType::Finally]) {
+            true => {
+                self.try_consume(TokenType::LeftBrace, "Expected '{' after 'finally'")?;
+                Some(self.block()?)
+            }
+            false => None,
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(LoxError::new(
+                keyword.line,
+                0,
+                keyword.lexeme.to_string(),
+                "Expected 'catch' or 'finally' after try block".to_owned(),
+            ));
+        }
+        Ok(Stmt::Try(Try::new(body, catch, finally)))
+    }
+
+    fn catch_clause(&mut self) -> Result<Catch, LoxError> {
+        self.try_consume(TokenType::LeftParen, "Expected '(' after 'catch'")?;
+        let name = self
+            .try_consume(TokenType::Identifier, "Expected error name")?
+            .clone();
+        self.try_consume(TokenType::RightParen, "Expected ')' after error name")?;
+        self.try_consume(TokenType::LeftBrace, "Expected '{' after catch clause")?;
+
+        // the error's name is scoped to the catch body
+        self.scopes
+            .push(HashMap::from([(name.lexeme.clone(), false)]));
+        let body = self.block();
+        self.scopes.pop();
+        Ok(Catch::new(name, body?))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, LoxError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn loop_jump_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            self.errors.push(LoxError::new(
+                keyword.line,
+                0,
+                keyword.lexeme.to_string(),
+                format!("Can't use '{}' outside of a loop", keyword.lexeme),
+            ));
+        }
+
+        self.try_consume(
+            TokenType::Semicolon,
+            &format!("Expected semicolon after '{}'", keyword.lexeme),
+        )?;
+        match keyword.token_type {
+            TokenType::Break => Ok(Stmt::Break),
+            _ => Ok(Stmt::Continue),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
+        self.scopes.push(HashMap::new());
+        let stmts = self.block_declarations();
+        self.scopes.pop();
+        stmts
+    }
+
+    fn block_declarations(&mut self) -> Result<Vec<Stmt>, LoxError> {
+        let mut stmts = vec![];
 
         while !(self.check(&TokenType::RightBrace) || self.is_at_end()) {
-            stmts.push(self.declaration(new_env.clone())?);
+            stmts.push(self.declaration()?);
         }
 
         self.try_consume(TokenType::RightBrace, "Expected '}' after block")?;
         Ok(stmts)
     }
 
-    fn expr_statement(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Stmt, LoxError> {
-        let expr = self.expression(environment)?;
+    // the innermost declaration of the name decides, a `var` can shadow an outer constant
+    fn declare(&mut self, name: &Token, constant: bool) {
+        self.check_redeclarable(name);
+        self.scope().insert(name.lexeme.clone(), constant);
+    }
+
+    // the innermost block's names
+    fn scope(&mut self) -> &mut HashMap<Rc<str>, bool> {
+        self.scopes
+            .last_mut()
+            .expect("global scope is never popped")
+    }
+
+    // a constant can only be shadowed from an inner scope, not redeclared in its own
+    fn check_redeclarable(&mut self, name: &Token) {
+        if self.scope().get(&name.lexeme) == Some(&true) {
+            self.errors.push(LoxError::new(
+                name.line,
+                0,
+                name.lexeme.to_string(),
+                "Cannot redeclare a constant".to_owned(),
+            ));
+        }
+    }
+
+    fn check_assignable(&mut self, name: &Token) {
+        let constant = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme))
+            .copied()
+            .unwrap_or(false);
+
+        if constant {
+            self.errors.push(LoxError::new(
+                name.line,
+                0,
+                name.lexeme.to_string(),
+                "Cannot assign to a constant".to_owned(),
+            ));
+        }
+    }
+
+    fn expr_statement(&mut self) -> Result<Stmt, LoxError> {
+        let expr = self.expression()?;
         self.try_consume(TokenType::Semicolon, "Expected semicolon after expression")?;
         Ok(Stmt::Expr(expr))
     }
 
-    fn expression(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        self.assignment(environment)
+    // assignment ( , assignment ) *
+    // anywhere commas already mean something (arguments, list items, map entries) parses
+    // assignment instead, so `f(a, b)` is still two arguments
+    fn expression(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.assignment()?;
+
+        if !self.check(&TokenType::Comma) {
+            return Ok(expr);
+        }
+        let mut exprs = vec![expr];
+        while self.adv_if_match(&[TokenType::Comma]) {
+            exprs.push(self.assignment()?);
+        }
+        Ok(Expr::Sequence(exprs))
     }
 
-    fn assignment(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        let mut expr = self.equality(environment.clone())?;
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.conditional()?;
 
         if self.adv_if_match(&[TokenType::Equal]) {
-            let value = self.assignment(environment.clone())?;
+            let value = self.assignment()?;
 
             expr = match expr {
-                Expr::Variable(name, _) => {
-                    Expr::Assign(Assign::new(name, Box::new(value)), environment.clone())
+                Expr::Variable(name) => {
+                    self.check_assignable(&name);
+                    Expr::Assign(Assign::new(name, Box::new(value)))
                 }
+                Expr::Index(index) => Expr::IndexAssign(IndexAssign::new(index, Box::new(value))),
                 _ => {
                     return Err(LoxError::new(
                         self.peek().line,
-                        self.current,
+                        0,
                         "".to_owned(),
                         "Invalid assignment target".to_owned(),
                     ))
                 }
             }
+        } else if self.adv_if_match(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+            TokenType::PercentEqual,
+        ]) {
+            let op = match self.previous().token_type {
+                TokenType::PlusEqual => BinaryOp::Plus,
+                TokenType::MinusEqual => BinaryOp::Minus,
+                TokenType::StarEqual => BinaryOp::Star,
+                TokenType::SlashEqual => BinaryOp::Slash,
+                TokenType::PercentEqual => BinaryOp::Percent,
+                _ => unreachable!(),
+            };
+            let value = self.assignment()?;
+            expr = self.update(expr, op, value, false)?;
+        }
+
+        Ok(expr)
+    }
+
+    // only variables and indices can be updated in place
+    fn update(
+        &mut self,
+        target: Expr,
+        op: BinaryOp,
+        value: Expr,
+        postfix: bool,
+    ) -> Result<Expr, LoxError> {
+        if let Expr::Variable(name) = &target {
+            self.check_assignable(name);
+        }
+
+        match target {
+            Expr::Variable(_) | Expr::Index(_) => {
+                Ok(Expr::Update(Update::new(target, op, value, postfix)))
+            }
+            _ => Err(LoxError::new(
+                self.peek().line,
+                0,
+                self.previous().lexeme.to_string(),
+                "Invalid assignment target".to_owned(),
+            )),
+        }
+    }
+
+    fn increment_op(&self) -> BinaryOp {
+        match self.previous().token_type {
+            TokenType::PlusPlus => BinaryOp::Plus,
+            TokenType::MinusMinus => BinaryOp::Minus,
+            _ => unreachable!(),
+        }
+    }
+
+    // or ( ? expression : conditional )?
+    fn conditional(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.or()?;
+
+        if self.adv_if_match(&[TokenType::Question]) {
+            let line = self.previous().line;
+            let then_expr = self.expression()?;
+            self.try_consume(TokenType::Colon, "Expected ':' in conditional expression")?;
+            let else_expr = self.conditional()?;
+            return Ok(Expr::Conditional(Conditional::new(
+                Box::new(expr),
+                Box::new(then_expr),
+                Box::new(else_expr),
+                line,
+            )));
+        }
+
+        Ok(expr)
+    }
+
+    // and ( or and ) *
+    fn or(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.and()?;
+
+        while self.adv_if_match(&[TokenType::Or]) {
+            let line = self.previous().line;
+            let right = self.and()?;
+            expr = Expr::Logical(Logical::new(
+                Box::new(expr),
+                LogicalOp::Or,
+                Box::new(right),
+                line,
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    // equality ( and equality ) *
+    fn and(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.equality()?;
+
+        while self.adv_if_match(&[TokenType::And]) {
+            let line = self.previous().line;
+            let right = self.equality()?;
+            expr = Expr::Logical(Logical::new(
+                Box::new(expr),
+                LogicalOp::And,
+                Box::new(right),
+                line,
+            ));
         }
 
         Ok(expr)
     }
 
     // comparison ( (== | !=) comparison ) *
-    fn equality(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        let mut expr = self.comparison(environment.clone())?;
+    fn equality(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.comparison()?;
         while self.adv_if_match(&[TokenType::EqualEqual, TokenType::BangEqual]) {
             let op = match self.previous().token_type {
                 TokenType::BangEqual => BinaryOp::BangEqual,
                 TokenType::EqualEqual => BinaryOp::EqualEqual,
                 _ => unreachable!(), // unreachable guaranteed by check in adv_if_match
             };
-            let right = self.comparison(environment.clone())?;
-            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right)));
+            let line = self.previous().line;
+            let right = self.comparison()?;
+            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right), line));
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        let mut expr = self.term(environment.clone())?;
+    // bit_or ( (< | <= | > | >=) bit_or ) *, chained like Python rather than nested
+    fn comparison(&mut self) -> Result<Expr, LoxError> {
+        let first = self.bit_or()?;
+        let mut rest = vec![];
+        let line = self.peek().line; // of the first operator, if there is one
 
         while self.adv_if_match(&[
             TokenType::Less,
@@ -164,15 +739,99 @@ impl Parser {
                 TokenType::GreaterEqual => BinaryOp::GreaterEqual,
                 _ => unreachable!(),
             };
-            let right = self.term(environment.clone())?;
-            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right)));
+            rest.push((op, self.bit_or()?));
+        }
+
+        match rest.len() {
+            0 => Ok(first),
+            1 => {
+                let (op, right) = rest.pop().unwrap();
+                Ok(Expr::Binary(Binary::new(
+                    Box::new(first),
+                    op,
+                    Box::new(right),
+                    line,
+                )))
+            }
+            _ => Ok(Expr::Comparison(Comparison::new(
+                Box::new(first),
+                rest,
+                line,
+            ))),
+        }
+    }
+
+    // bitwise operators bind tighter than comparisons, as in Python: `a & b == c` is `(a & b) == c`
+    fn bit_or(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.adv_if_match(&[TokenType::Pipe]) {
+            let line = self.previous().line;
+            let right = self.bit_xor()?;
+            expr = Expr::Binary(Binary::new(
+                Box::new(expr),
+                BinaryOp::Pipe,
+                Box::new(right),
+                line,
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bit_and()?;
+
+        while self.adv_if_match(&[TokenType::Caret]) {
+            let line = self.previous().line;
+            let right = self.bit_and()?;
+            expr = Expr::Binary(Binary::new(
+                Box::new(expr),
+                BinaryOp::Caret,
+                Box::new(right),
+                line,
+            ));
         }
 
         Ok(expr)
     }
 
-    fn term(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        let mut expr = self.factor(environment.clone())?;
+    fn bit_and(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.shift()?;
+
+        while self.adv_if_match(&[TokenType::Ampersand]) {
+            let line = self.previous().line;
+            let right = self.shift()?;
+            expr = Expr::Binary(Binary::new(
+                Box::new(expr),
+                BinaryOp::Ampersand,
+                Box::new(right),
+                line,
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.term()?;
+
+        while self.adv_if_match(&[TokenType::LessLess, TokenType::GreaterGreater]) {
+            let op = match self.previous().token_type {
+                TokenType::LessLess => BinaryOp::LessLess,
+                TokenType::GreaterGreater => BinaryOp::GreaterGreater,
+                _ => unreachable!(),
+            };
+            let line = self.previous().line;
+            let right = self.term()?;
+            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right), line));
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.factor()?;
 
         while self.adv_if_match(&[TokenType::Minus, TokenType::Plus]) {
             let op = match self.previous().token_type {
@@ -180,45 +839,127 @@ impl Parser {
                 TokenType::Plus => BinaryOp::Plus,
                 _ => unreachable!(),
             };
-            let right = self.factor(environment.clone())?;
-            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right)));
+            let line = self.previous().line;
+            let right = self.factor()?;
+            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right), line));
         }
 
         Ok(expr)
     }
 
-    fn factor(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        let mut expr = self.unary(environment.clone())?;
+    fn factor(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.unary()?;
 
-        while self.adv_if_match(&[TokenType::Slash, TokenType::Star]) {
+        while self.adv_if_match(&[
+            TokenType::Slash,
+            TokenType::Star,
+            TokenType::Percent,
+            TokenType::TildeSlash,
+        ]) {
             let op = match self.previous().token_type {
                 TokenType::Slash => BinaryOp::Slash,
                 TokenType::Star => BinaryOp::Star,
+                TokenType::Percent => BinaryOp::Percent,
+                TokenType::TildeSlash => BinaryOp::TildeSlash,
                 _ => unreachable!(),
             };
-            let right = self.unary(environment.clone())?;
-            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right)));
+            let line = self.previous().line;
+            let right = self.unary()?;
+            expr = Expr::Binary(Binary::new(Box::new(expr), op, Box::new(right), line));
         }
 
         Ok(expr)
     }
 
-    fn unary(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
-        match self.adv_if_match(&[TokenType::Minus, TokenType::Bang]) {
+    fn unary(&mut self) -> Result<Expr, LoxError> {
+        if self.adv_if_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let op = self.increment_op();
+            let target = self.unary()?;
+            return self.update(target, op, Expr::Literal(Literal::Integer(1)), false);
+        }
+
+        match self.adv_if_match(&[TokenType::Minus, TokenType::Bang, TokenType::Tilde]) {
             true => {
                 let op = match self.previous().token_type {
                     TokenType::Minus => UnaryOp::Minus,
                     TokenType::Bang => UnaryOp::Bang,
+                    TokenType::Tilde => UnaryOp::Tilde,
                     _ => unreachable!(),
                 };
-                let expr = self.unary(environment)?;
-                Ok(Expr::Unary(Unary::new(op, Box::new(expr))))
+                let line = self.previous().line;
+                let expr = self.unary()?;
+                Ok(Expr::Unary(Unary::new(op, Box::new(expr), line)))
+            }
+            false => self.power(),
+        }
+    }
+
+    // call ( ** unary )?
+    // right associative and tighter than unary minus on its left, so -2 ** 2 is -(2 ** 2),
+    // while the exponent itself may still be negated: 2 ** -1
+    fn power(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.call()?;
+
+        if self.adv_if_match(&[TokenType::StarStar]) {
+            let line = self.previous().line;
+            let right = self.unary()?;
+            return Ok(Expr::Binary(Binary::new(
+                Box::new(expr),
+                BinaryOp::StarStar,
+                Box::new(right),
+                line,
+            )));
+        }
+
+        Ok(expr)
+    }
+
+    // primary ( "(" arguments? ")" | "[" expression "]" | "." IDENTIFIER ) * ( "++" | "--" )?
+    fn call(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.adv_if_match(&[TokenType::LeftParen]) {
+                let line = self.previous().line;
+                let args = self.comma_separated(TokenType::RightParen)?;
+                self.try_consume(TokenType::RightParen, "Expected ')' after arguments")?;
+                expr = Expr::Call(Call::new(Box::new(expr), args, line));
+            } else if self.adv_if_match(&[TokenType::LeftBracket]) {
+                let line = self.previous().line;
+                let index = self.expression()?;
+                self.try_consume(TokenType::RightBracket, "Expected ']' after index")?;
+                expr = Expr::Index(Index::new(Box::new(expr), Box::new(index), line));
+            } else if self.adv_if_match(&[TokenType::Dot]) {
+                let name = self
+                    .try_consume(TokenType::Identifier, "Expected property name after '.'")?
+                    .clone();
+                expr = Expr::Get(Get::new(Box::new(expr), name));
+            } else {
+                break;
             }
-            false => self.primary(environment),
         }
+
+        if self.adv_if_match(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let op = self.increment_op();
+            expr = self.update(expr, op, Expr::Literal(Literal::Integer(1)), true)?;
+        }
+
+        Ok(expr)
     }
 
-    fn primary(&mut self, environment: Rc<RefCell<Environment>>) -> Result<Expr, LoxError> {
+    // expressions separated by commas, a trailing comma before `closing` is allowed
+    fn comma_separated(&mut self, closing: TokenType) -> Result<Vec<Expr>, LoxError> {
+        let mut exprs = vec![];
+        while !self.check(&closing) {
+            exprs.push(self.assignment()?);
+            if !self.adv_if_match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        Ok(exprs)
+    }
+
+    fn primary(&mut self) -> Result<Expr, LoxError> {
         if self.adv_if_match(&[TokenType::False]) {
             Ok(Expr::Literal(Literal::Boolean(false)))
         } else if self.adv_if_match(&[TokenType::True]) {
@@ -227,22 +968,55 @@ impl Parser {
             Ok(Expr::Literal(Literal::Nil))
         } else if self.adv_if_match(&[TokenType::Number, TokenType::String]) {
             let lit = match self.previous().literal.as_ref().unwrap() {
+                tokens::Literal::Integer(i) => Literal::Integer(*i),
                 tokens::Literal::Number(n) => Literal::Number(*n),
-                tokens::Literal::String(s) => Literal::String(s.clone()),
+                tokens::Literal::String(s) => Literal::String(s.to_string()),
                 _ => unreachable!(),
             };
             Ok(Expr::Literal(lit))
+        } else if self.adv_if_match(&[TokenType::LeftBracket]) {
+            let line = self.previous().line;
+            let items = self.comma_separated(TokenType::RightBracket)?;
+            self.try_consume(TokenType::RightBracket, "Expected ']' after list items")?;
+            Ok(Expr::List(ListLiteral::new(items, line)))
+        } else if self.adv_if_match(&[TokenType::LeftBrace]) {
+            let line = self.previous().line;
+            let mut entries = vec![];
+            while !self.check(&TokenType::RightBrace) {
+                let key = self.assignment()?;
+                self.try_consume(TokenType::Colon, "Expected ':' after map key")?;
+                let val = self.assignment()?;
+                entries.push((key, val));
+                if !self.adv_if_match(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.try_consume(TokenType::RightBrace, "Expected '}' after map entries")?;
+            Ok(Expr::Map(MapLiteral::new(entries, line)))
+        } else if self.adv_if_match(&[TokenType::Fun]) {
+            self.try_consume(TokenType::LeftParen, "Expected '(' after 'fun'")?;
+            Ok(Expr::Function(Rc::new(self.function_rest(None)?)))
+        } else if self.check(&TokenType::LeftParen) && self.at_arrow_function() {
+            // (params) => expression is short for fun (params) { return expression; }
+            self.advance();
+            let (params, rest) = self.parameters()?;
+            self.try_consume(TokenType::Arrow, "Expected '=>' after parameters")?;
+            let body = self.function_body(&params, &rest, |parser| parser.assignment())?;
+            let body = vec![Stmt::Return(Return::new(Some(body)))];
+            Ok(Expr::Function(Rc::new(Function::new(
+                None, params, rest, body,
+            ))))
         } else if self.adv_if_match(&[TokenType::LeftParen]) {
-            let expr = self.expression(environment)?;
+            let expr = self.expression()?;
             self.try_consume(TokenType::RightParen, "')' Expected after expression")?;
             Ok(Expr::Grouping(Grouping::new(Box::new(expr))))
         } else if self.adv_if_match(&[TokenType::Identifier]) {
-            Ok(Expr::Variable(self.previous().clone(), environment.clone())) //TODO: replace call to previous().clone() with reference maybe?
+            Ok(Expr::Variable(self.previous().clone())) //TODO: replace call to previous().clone() with reference maybe?
         } else {
             Err(LoxError::new(
-                self.tokens[self.current as usize].line,
+                self.peek().line,
                 0,
-                self.tokens[self.current as usize].lexeme.clone(),
+                self.peek().lexeme.to_string(),
                 "Unexpected character encountered".to_owned(),
             ))
         }
@@ -257,11 +1031,15 @@ impl Parser {
 
             match self.peek().token_type {
                 TokenType::Class
+                | TokenType::Const
                 | TokenType::Fun
                 | TokenType::For
                 | TokenType::If
+                | TokenType::Import
                 | TokenType::Print
                 | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try
                 | TokenType::Var
                 | TokenType::While => return,
                 _ => (),
@@ -282,7 +1060,7 @@ impl Parser {
     }
 
     fn previous(&self) -> &Token {
-        &self.tokens[(self.current - 1) as usize]
+        &self.previous
     }
 
     fn check(&self, t: &TokenType) -> bool {
@@ -297,12 +1075,32 @@ impl Parser {
     }
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.current as usize]
+        &self.current
+    }
+
+    // n tokens past the current one, Eof if the input ends before that
+    fn peek_ahead(&mut self, n: usize) -> &Token {
+        while self.lookahead.len() < n {
+            let last = self.lookahead.back().unwrap_or(&self.current);
+            if last.token_type == TokenType::Eof {
+                break;
+            }
+            let next = Self::next_token(&mut self.tokens, &mut self.scan_errors);
+            self.lookahead.push_back(next);
+        }
+        self.lookahead
+            .get(n - 1)
+            .or(self.lookahead.back())
+            .unwrap_or(&self.current)
     }
 
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
-            self.current += 1;
+            let next = match self.lookahead.pop_front() {
+                Some(token) => token,
+                None => Self::next_token(&mut self.tokens, &mut self.scan_errors),
+            };
+            self.previous = std::mem::replace(&mut self.current, next);
         }
         self.previous()
     }
@@ -310,11 +1108,116 @@ impl Parser {
     fn try_consume(&mut self, token_type: TokenType, err_msg: &str) -> Result<&Token, LoxError> {
         let peek = self.peek();
         let line = peek.line;
-        let chars_in_line = peek.lexeme.clone(); // don't remember if this is actually the chars in line lol AND I DONT EFFIN CARE!!!!!!!!!
+        let chars_in_line = peek.lexeme.to_string(); // don't remember if this is actually the chars in line lol AND I DONT EFFIN CARE!!!!!!!!!
         if self.check(&token_type) {
-            return Ok(self.advance());
+            Ok(self.advance())
         } else {
             Err(LoxError::new(line, 0, chars_in_line, err_msg.to_owned()))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, Options};
+
+    fn parse(source: &str) -> Vec<Result<Stmt, LoxError>> {
+        Parser::new(Scanner::new(source)).parse()
+    }
+
+    // the messages of every error, in order
+    fn errors(source: &str) -> Vec<String> {
+        parse(source)
+            .into_iter()
+            .filter_map(Result::err)
+            .map(|err| err.message)
+            .collect()
+    }
+
+    // the expression of a lone expression statement
+    fn expr(source: &str) -> Expr {
+        match parse(source).pop() {
+            Some(Ok(Stmt::Expr(expr))) => expr,
+            _ => panic!("{} isn't an expression statement", source),
+        }
+    }
+
+    #[test]
+    fn parenthesized_parameters_followed_by_an_arrow_are_a_function() {
+        let Expr::Function(function) = expr("(a, b) => a + b;") else {
+            panic!("not a function");
+        };
+        assert_eq!(function.params.len(), 2);
+        assert!(matches!(expr("() => 1;"), Expr::Function(_)));
+        assert!(matches!(expr("(...xs) => xs;"), Expr::Function(_)));
+        assert!(matches!(
+            expr("(a = (1 + 2), b = f(3)) => a;"),
+            Expr::Function(_)
+        ));
+    }
+
+    #[test]
+    fn parentheses_without_an_arrow_are_a_grouping() {
+        assert!(matches!(expr("((1 + 2));"), Expr::Grouping(_)));
+        assert!(matches!(expr("(a);"), Expr::Grouping(_)));
+        assert!(matches!(expr("(x, y);"), Expr::Grouping(_)));
+
+        // and a sequence inside is still evaluated as one
+        let mut interpreter = Interpreter::new(Options::default(), "<test>".into(), vec![]);
+        let value = expr("(1, 2);").interpret_ast(&mut interpreter);
+        assert!(matches!(value, Ok(Literal::Integer(2))));
+    }
+
+    #[test]
+    fn a_brace_followed_by_a_key_and_colon_starts_a_map() {
+        assert!(matches!(expr(r#"{"a": 1};"#), Expr::Map(_)));
+        assert!(matches!(expr("{ x: 1 };"), Expr::Map(_)));
+    }
+
+    #[test]
+    fn other_braces_at_the_start_of_a_statement_are_blocks() {
+        assert!(matches!(parse("{}").pop(), Some(Ok(Stmt::Block(_)))));
+        let Some(Ok(Stmt::Block(stmts))) = parse("{ a ? b : c; }").pop() else {
+            panic!("not a block");
+        };
+        assert!(matches!(stmts[..], [Stmt::Expr(Expr::Conditional(_))]));
+    }
+
+    #[test]
+    fn assigning_to_a_constant_is_reported_without_follow_on_errors() {
+        assert_eq!(
+            errors("const c = 1; fun f() { c = 2; }"),
+            ["Cannot assign to a constant"]
+        );
+        assert_eq!(
+            errors("const c = 1; c += 1; c++;"),
+            ["Cannot assign to a constant", "Cannot assign to a constant"]
+        );
+        assert_eq!(
+            errors("const c = 1; const c = 2;"),
+            ["Cannot redeclare a constant"]
+        );
+    }
+
+    #[test]
+    fn shadowing_a_constant_is_allowed() {
+        assert!(errors("const c = 1; { var c = 2; c = 3; }").is_empty());
+        assert!(errors("const c = 1; fun f(c) { c = 2; }").is_empty());
+    }
+
+    #[test]
+    fn break_and_continue_only_work_inside_loops() {
+        assert!(errors("while (true) { break; } for (;;) { continue; }").is_empty());
+        assert!(errors("while (true) { if (true) { break; } }").is_empty());
+        assert_eq!(
+            errors("continue;"),
+            ["Can't use 'continue' outside of a loop"]
+        );
+        // a function body starts outside of any loop, even inside one
+        assert_eq!(
+            errors("while (true) { fun f() { break; } }"),
+            ["Can't use 'break' outside of a loop"]
+        );
+    }
+}
@@ -1,11 +1,24 @@
-use crate::{
-    tokens::{Literal, Token, TokenType},
-    Lox,
-};
+use std::{collections::HashSet, rc::Rc};
 
+use crate::tokens::{self, Literal, Token, TokenType};
+
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub line: u32,
+    pub loc_in_line: u32,
+    pub chars_in_line: String,
+    pub message: String,
+}
+
+// Scans lazily, one token per call to next(); collect it if the whole Vec<Token> is needed.
+// Errors don't stop the scan, they're handed out in order between the tokens.
 pub struct Scanner<'a> {
-    source: String,
-    tokens: Vec<Token>,
+    source: &'a str,
+    // every lexeme is interned, so repeated identifiers/operators share one allocation
+    symbols: HashSet<Rc<str>>,
+    // set by add_token/error, scan_token loops until one of them produces something
+    scanned: Option<Result<Token, ScanError>>,
+    reached_eof: bool,
 
     // lexeme-specific, start and current are offsets in the string
     // start: start of word being scanned,
@@ -14,36 +27,34 @@ pub struct Scanner<'a> {
     start: u32,
     current: u32,
     line: u32,
-    lox: &'a mut Lox,
 }
 
 impl<'a> Scanner<'a> {
-    pub fn new(source: String, lox: &'a mut Lox) -> Self {
+    pub fn new(source: &'a str) -> Self {
         Self {
             source,
-            tokens: vec![],
+            symbols: HashSet::new(),
+            scanned: None,
+            reached_eof: false,
             start: 0,
             current: 0,
-            line: 0,
-            lox,
+            line: 1,
         }
     }
 
-    //TODO: can be abstracted into a separate type-state, e.g. ScannedTokens?
-    // consumes, scanner presumably won't be used after getting the tokens
-    pub fn tokens(self) -> Vec<Token> {
-        self.tokens
-    }
-
-    pub fn scan_tokens(&mut self) {
-        while !self.is_at_end() {
-            self.start = self.current;
-
-            self.scan_token();
+    // the whole scan at once, for callers that don't want to pull tokens one at a time (tests,
+    // tooling). Errors don't stop the scan, so tokens are always returned alongside them
+    #[allow(dead_code)] // the interpreter itself only uses the iterator
+    pub fn scan_tokens(self) -> (Vec<Token>, Vec<ScanError>) {
+        let mut tokens = vec![];
+        let mut errors = vec![];
+        for scanned in self {
+            match scanned {
+                Ok(token) => tokens.push(token),
+                Err(err) => errors.push(err),
+            }
         }
-
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_owned(), None, self.line));
+        (tokens, errors)
     }
 
     // note: not important but possibly somewhere to use #[inline]
@@ -59,12 +70,71 @@ impl<'a> Scanner<'a> {
             ')' => self.add_token(TokenType::RightParen, None),
             '{' => self.add_token(TokenType::LeftBrace, None),
             '}' => self.add_token(TokenType::RightBrace, None),
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
+            ':' => self.add_token(TokenType::Colon, None),
+            '?' => self.add_token(TokenType::Question, None),
             ',' => self.add_token(TokenType::Comma, None),
-            '.' => self.add_token(TokenType::Dot, None),
-            '-' => self.add_token(TokenType::Minus, None),
-            '+' => self.add_token(TokenType::Plus, None),
+            '.' => {
+                let token_type = if self.peek() == '.' && self.peek_next() == '.' {
+                    self.advance();
+                    self.advance();
+                    TokenType::DotDotDot
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(token_type, None)
+            }
+            '-' => {
+                let token_type = if self.match_token('-') {
+                    TokenType::MinusMinus
+                } else if self.match_token('=') {
+                    TokenType::MinusEqual
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(token_type, None)
+            }
+            '+' => {
+                let token_type = if self.match_token('+') {
+                    TokenType::PlusPlus
+                } else if self.match_token('=') {
+                    TokenType::PlusEqual
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(token_type, None)
+            }
             ';' => self.add_token(TokenType::Semicolon, None),
-            '*' => self.add_token(TokenType::Star, None),
+            '*' => {
+                let token_type = if self.match_token('*') {
+                    TokenType::StarStar
+                } else if self.match_token('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(token_type, None)
+            }
+            '%' => {
+                let token_type = if self.match_token('=') {
+                    TokenType::PercentEqual
+                } else {
+                    TokenType::Percent
+                };
+                self.add_token(token_type, None)
+            }
+            '&' => self.add_token(TokenType::Ampersand, None),
+            '|' => self.add_token(TokenType::Pipe, None),
+            '^' => self.add_token(TokenType::Caret, None),
+            '~' => {
+                let token_type = if self.match_token('/') {
+                    TokenType::TildeSlash
+                } else {
+                    TokenType::Tilde
+                };
+                self.add_token(token_type, None)
+            }
             '!' => {
                 let token_type = if self.match_token('=') {
                     TokenType::BangEqual
@@ -76,6 +146,8 @@ impl<'a> Scanner<'a> {
             '=' => {
                 let token_type = if self.match_token('=') {
                     TokenType::EqualEqual
+                } else if self.match_token('>') {
+                    TokenType::Arrow
                 } else {
                     TokenType::Equal
                 };
@@ -84,6 +156,8 @@ impl<'a> Scanner<'a> {
             '>' => {
                 let token_type = if self.match_token('=') {
                     TokenType::GreaterEqual
+                } else if self.match_token('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 };
@@ -92,6 +166,8 @@ impl<'a> Scanner<'a> {
             '<' => {
                 let token_type = if self.match_token('=') {
                     TokenType::LessEqual
+                } else if self.match_token('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 };
@@ -102,6 +178,8 @@ impl<'a> Scanner<'a> {
                     while !self.is_at_end() && self.peek() != '\n' {
                         self.advance();
                     }
+                } else if self.match_token('=') {
+                    self.add_token(TokenType::SlashEqual, None);
                 } else {
                     self.add_token(TokenType::Slash, None);
                 }
@@ -114,20 +192,35 @@ impl<'a> Scanner<'a> {
             _ => {
                 if c.is_ascii_digit() {
                     self.number();
-                } else if c.is_ascii_alphabetic() {
+                } else if is_alpha(c) {
                     self.identifier();
                 } else {
-                    self.lox.report(
-                        self.line,
-                        self.start,
-                        &self.source[self.start as usize..self.current as usize],
-                        "Unexpected character",
-                    );
+                    self.error("Unexpected character");
                 }
             }
         };
     }
 
+    fn error(&mut self, message: &str) {
+        self.scanned = Some(Err(ScanError {
+            line: self.line,
+            loc_in_line: self.start,
+            chars_in_line: self.source[self.start as usize..self.current as usize].to_owned(),
+            message: message.to_owned(),
+        }));
+    }
+
+    fn intern(&mut self, text: &str) -> Rc<str> {
+        match self.symbols.get(text) {
+            Some(symbol) => symbol.clone(),
+            None => {
+                let symbol: Rc<str> = Rc::from(text);
+                self.symbols.insert(symbol.clone());
+                symbol
+            }
+        }
+    }
+
     fn match_token(&mut self, expected: char) -> bool {
         if self.is_at_end() || self.peek() != expected {
             false
@@ -138,23 +231,23 @@ impl<'a> Scanner<'a> {
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.as_bytes()[self.current as usize];
-        self.current += 1;
-        c as char
+        let c = self.peek();
+        self.current += c.len_utf8() as u32;
+        c
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        // note: will panic if start..current doesn't encompass a valid character sequence
-        let text = &self.source[self.start as usize..self.current as usize]; // no off by one, advance() increments current by 1
-        let token = Token::new(token_type, text.to_owned(), literal, self.line);
-        self.tokens.push(token);
+        // start..current always lands on char boundaries since advance() steps whole chars
+        let lexeme = self.intern(&self.source[self.start as usize..self.current as usize]);
+        let token = Token::new(token_type, lexeme, literal, self.line);
+        self.scanned = Some(Ok(token));
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.as_bytes()[self.current as usize] as char
+        self.source[self.current as usize..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
     }
 
     fn string(&mut self) {
@@ -166,17 +259,15 @@ impl<'a> Scanner<'a> {
         }
 
         if self.is_at_end() {
-            self.lox.report(
-                self.line,
-                self.start,
-                &self.source[self.start as usize..self.current as usize],
-                "Unterminated string",
-            );
+            self.error("Unterminated string");
+            // nothing left to close the string with, so don't consume past the end
+            return;
         }
 
         self.advance(); // consume closing "
 
-        let string = self.source[(self.start + 1) as usize..(self.current - 1) as usize].to_owned();
+        let string =
+            self.intern(&self.source[(self.start + 1) as usize..(self.current - 1) as usize]);
 
         self.add_token(TokenType::String, Some(Literal::String(string)));
     }
@@ -186,60 +277,110 @@ impl<'a> Scanner<'a> {
             self.advance();
         }
 
-        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+        // `42` is an integer, `42.0` is a float
+        let is_float = self.peek() == '.' && self.peek_next().is_ascii_digit();
+        if is_float {
+            self.advance(); // consume the .
             while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
 
-        let num = match self.source[self.start as usize..self.current as usize].parse::<f64>() {
-            Ok(num) => num,
-            Err(_) => {
-                self.lox.report(
-                    self.line,
-                    self.start,
-                    &self.source[self.start as usize..self.current as usize],
-                    "Expected number but failed to parse",
-                );
-                return;
-            }
+        let text = &self.source[self.start as usize..self.current as usize];
+        let literal = match is_float {
+            true => text.parse::<f64>().map(Literal::Number).ok(),
+            false => text.parse::<i64>().map(Literal::Integer).ok(),
         };
-        self.add_token(TokenType::Number, Some(Literal::Number(num)));
+        match literal {
+            Some(literal) => self.add_token(TokenType::Number, Some(literal)),
+            None if is_float => self.error("Expected number but failed to parse"),
+            None => self.error("Integer literal too large"),
+        }
     }
 
     fn peek_next(&self) -> char {
-        if (self.current + 1) as usize >= self.source.len() {
-            return '\0';
-        }
-        self.source.as_bytes()[(self.current + 1) as usize] as char
+        let mut chars = self.source[self.current as usize..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() {
+        while is_alpha(self.peek()) || self.peek().is_ascii_digit() {
             self.advance();
         }
 
-        match &self.source[self.start as usize..self.current as usize] {
-            "and" => self.add_token(TokenType::And, None),
-            "class" => self.add_token(TokenType::Class, None),
-            "else" => self.add_token(TokenType::Else, None),
-            "false" => self.add_token(TokenType::False, None),
-            "for" => self.add_token(TokenType::For, None),
-            "fun" => self.add_token(TokenType::Fun, None),
-            "if" => self.add_token(TokenType::If, None),
-            "nil" => self.add_token(TokenType::Nil, None),
-            "or" => self.add_token(TokenType::Or, None),
-            "print" => self.add_token(TokenType::Print, None),
-            "return" => self.add_token(TokenType::Return, None),
-            "super" => self.add_token(TokenType::Super, None),
-            "this" => self.add_token(TokenType::This, None),
-            "true" => self.add_token(TokenType::True, None),
-            "var" => self.add_token(TokenType::Var, None),
-            "while" => self.add_token(TokenType::While, None),
-            user_literal => self.add_token(
-                TokenType::Identifier,
-                Some(Literal::Identifier(user_literal.to_owned())),
-            ),
+        let text = &self.source[self.start as usize..self.current as usize];
+        match tokens::keyword(text) {
+            Some(token_type) => self.add_token(token_type, None),
+            None => {
+                let name = self.intern(text);
+                self.add_token(TokenType::Identifier, Some(Literal::Identifier(name)))
+            }
         }
     }
 }
+
+// identifiers may start with (and contain) an underscore, as in the Lox spec
+fn is_alpha(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.is_at_end() {
+            self.start = self.current;
+
+            self.scan_token();
+            if let Some(scanned) = self.scanned.take() {
+                return Some(scanned);
+            }
+        }
+
+        if self.reached_eof {
+            return None;
+        }
+        self.reached_eof = true;
+        let lexeme = self.intern("");
+        Some(Ok(Token::new(TokenType::Eof, lexeme, None, self.line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_types(tokens: &[Token]) -> Vec<TokenType> {
+        tokens
+            .iter()
+            .map(|token| token.token_type.clone())
+            .collect()
+    }
+
+    #[test]
+    fn unterminated_string_is_an_error_not_a_panic() {
+        let (tokens, errors) = Scanner::new("var s = \"abc").scan_tokens();
+
+        assert_eq!(
+            token_types(&tokens),
+            [
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Eof
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated string");
+    }
+
+    #[test]
+    fn unterminated_string_spanning_lines() {
+        let (tokens, errors) = Scanner::new("\"abc\ndef\n").scan_tokens();
+
+        assert_eq!(token_types(&tokens), [TokenType::Eof]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(tokens[0].line, 3);
+    }
+}
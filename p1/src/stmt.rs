@@ -1,8 +1,11 @@
-use std::{cell::RefCell, rc::Rc};
+use std::rc::Rc;
 
 use crate::{
+    callable::{Callable, Closure},
     environment::Environment,
+    error::RuntimeError,
     expr::{Expr, Literal},
+    interpreter::Interpreter,
     tokens::Token,
 };
 
@@ -10,36 +13,146 @@ pub enum Stmt {
     Expr(Expr),
     Print(Expr),
     Block(Vec<Stmt>),
-    Var(Var, Rc<RefCell<Environment>>),
+    Var(Var),
+    If(If),
+    While(While),
+    Break,
+    Continue,
+    Throw(Throw),
+    Try(Try),
+    Function(Rc<Function>),
+    Return(Return),
+    Import(Import),
+}
+
+// how a statement finished, anything but Normal unwinds to the enclosing loop or function
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Literal),
 }
 
 impl Stmt {
-    pub fn interpret_stmt(self) -> Result<(), String> {
+    pub fn interpret_stmt(&self, interpreter: &mut Interpreter) -> Result<Flow, RuntimeError> {
+        interpreter.check_stack()?;
         match self {
             Stmt::Expr(expr) => {
-                expr.interpret_ast()?;
-                Ok(())
+                expr.interpret_ast(interpreter)?;
+                Ok(Flow::Normal)
             }
             Stmt::Print(expr) => {
-                let value = expr.interpret_ast()?;
+                let value = expr.interpret_ast(interpreter)?;
                 println!("{}", value);
-                Ok(())
+                Ok(Flow::Normal)
+            }
+            Stmt::Block(stmts) => interpreter.execute_block(stmts),
+            Stmt::Var(var) => {
+                let val = match &var.initializer {
+                    Some(expr) => expr.interpret_ast(interpreter)?,
+                    None => Literal::Nil,
+                };
+                let name = var.token.lexeme.to_string();
+                let defined = match var.constant {
+                    true => interpreter.define_const(name, val),
+                    false => interpreter.define(name, val),
+                };
+                defined.map_err(|err| err.at(var.token.line))?;
+                Ok(Flow::Normal)
+            }
+            Stmt::If(if_stmt) => {
+                let condition = if_stmt.condition.interpret_ast(interpreter)?;
+                let truthy = interpreter.is_truthy(&condition);
+                if truthy.map_err(|err| err.at(if_stmt.line))? {
+                    if_stmt.then_branch.interpret_stmt(interpreter)
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    else_branch.interpret_stmt(interpreter)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::While(while_stmt) => {
+                loop {
+                    let condition = while_stmt.condition.interpret_ast(interpreter)?;
+                    let truthy = interpreter.is_truthy(&condition);
+                    if !truthy.map_err(|err| err.at(while_stmt.line))? {
+                        break;
+                    }
+                    match while_stmt.body.interpret_stmt(interpreter)? {
+                        Flow::Break => break,
+                        Flow::Return(val) => return Ok(Flow::Return(val)),
+                        Flow::Normal | Flow::Continue => (),
+                    }
+                    // after Normal and Continue alike
+                    if let Some(increment) = &while_stmt.increment {
+                        increment.interpret_ast(interpreter)?;
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::Break => Ok(Flow::Break),
+            Stmt::Continue => Ok(Flow::Continue),
+            Stmt::Throw(throw) => {
+                let value = throw.expr.interpret_ast(interpreter)?;
+                Err(RuntimeError::throw(value, throw.line))
             }
-            Stmt::Block(stmts) => {
-                stmts
-                    .into_iter()
-                    .try_for_each(|stmt| stmt.interpret_stmt())?;
-                Ok(())
+            Stmt::Function(function) => {
+                let closure = Closure::new(
+                    function.clone(),
+                    interpreter.environment(),
+                    interpreter.script(),
+                );
+                interpreter
+                    .define(
+                        function.name().to_owned(),
+                        Literal::Callable(Callable::Function(Rc::new(closure))),
+                    )
+                    .map_err(|err| match &function.name {
+                        Some(name) => err.at(name.line),
+                        None => err,
+                    })?;
+                Ok(Flow::Normal)
             }
-            Stmt::Var(var, env) => {
-                match var.initializer {
-                    Some(expr) => {
-                        let val = expr.interpret_ast()?;
-                        env.borrow_mut().define(var.token.lexeme, val)
+            Stmt::Return(return_stmt) => {
+                let val = match &return_stmt.value {
+                    Some(expr) => expr.interpret_ast(interpreter)?,
+                    None => Literal::Nil,
+                };
+                Ok(Flow::Return(val))
+            }
+            Stmt::Import(import) => {
+                let module = interpreter
+                    .import(&import.path, import.line)
+                    .map_err(|err| err.at(import.line))?;
+                let name = match &import.alias {
+                    Some(alias) => alias.lexeme.to_string(),
+                    None => module.name().to_owned(),
+                };
+                interpreter
+                    .define(name, Literal::Module(module))
+                    .map_err(|err| err.at(import.line))?;
+                Ok(Flow::Normal)
+            }
+            Stmt::Try(try_stmt) => {
+                let result = match (interpreter.execute_block(&try_stmt.body), &try_stmt.catch) {
+                    (Err(err), Some(catch)) if err.exit_code().is_none() => {
+                        let mut scope = Environment::with_enclosing(interpreter.environment());
+                        scope
+                            .define(catch.name.lexeme.to_string(), err.into_value())
+                            .expect("the catch scope starts out empty");
+                        interpreter.execute_block_in(&catch.body, scope)
                     }
-                    None => env.borrow_mut().define(var.token.lexeme, Literal::Nil),
+                    (result, _) => result,
                 };
-                Ok(())
+                // runs however the rest finished, and only replaces that if it doesn't finish
+                // normally itself (an error or a break out of it)
+                match &try_stmt.finally {
+                    Some(finally) => match interpreter.execute_block(finally)? {
+                        Flow::Normal => result,
+                        flow => Ok(flow),
+                    },
+                    None => result,
+                }
             }
         }
     }
@@ -48,6 +161,7 @@ impl Stmt {
 pub struct Var {
     token: Token,
     initializer: Option<Expr>,
+    constant: bool,
 }
 
 impl Var {
@@ -55,6 +169,7 @@ impl Var {
         Self {
             token,
             initializer: None,
+            constant: false,
         }
     }
 
@@ -62,6 +177,175 @@ impl Var {
         Self {
             token,
             initializer: Some(initializer),
+            constant: false,
         }
     }
+
+    // constants always have an initializer, there'd be no way to give them a value later
+    pub fn constant(token: Token, initializer: Expr) -> Self {
+        Self {
+            token,
+            initializer: Some(initializer),
+            constant: true,
+        }
+    }
+}
+
+pub struct If {
+    condition: Expr,
+    line: u32, // where the condition starts, for when it isn't a boolean in strict mode
+    then_branch: Box<Stmt>,
+    else_branch: Option<Box<Stmt>>,
+}
+
+impl If {
+    pub fn new(condition: Expr, line: u32, then_branch: Stmt, else_branch: Option<Stmt>) -> Self {
+        Self {
+            condition,
+            line,
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+        }
+    }
+}
+
+pub struct While {
+    condition: Expr,
+    line: u32, // where the condition starts, like If's
+    body: Box<Stmt>,
+    increment: Option<Expr>, // only set for `for` loops, kept apart so `continue` still runs it
+}
+
+impl While {
+    pub fn new(condition: Expr, line: u32, body: Stmt) -> Self {
+        Self {
+            condition,
+            line,
+            body: Box::new(body),
+            increment: None,
+        }
+    }
+
+    pub fn with_increment(condition: Expr, line: u32, body: Stmt, increment: Option<Expr>) -> Self {
+        Self {
+            condition,
+            line,
+            body: Box::new(body),
+            increment,
+        }
+    }
+}
+
+pub struct Throw {
+    expr: Expr,
+    line: u32,
+}
+
+impl Throw {
+    pub fn new(expr: Expr, line: u32) -> Self {
+        Self { expr, line }
+    }
+}
+
+pub struct Catch {
+    name: Token, // what the error is bound to inside the body
+    body: Vec<Stmt>,
+}
+
+impl Catch {
+    pub fn new(name: Token, body: Vec<Stmt>) -> Self {
+        Self { name, body }
+    }
+}
+
+// has at least one of catch and finally
+pub struct Try {
+    body: Vec<Stmt>,
+    catch: Option<Catch>,
+    finally: Option<Vec<Stmt>>,
+}
+
+impl Try {
+    pub fn new(body: Vec<Stmt>, catch: Option<Catch>, finally: Option<Vec<Stmt>>) -> Self {
+        Self {
+            body,
+            catch,
+            finally,
+        }
+    }
+}
+
+pub struct Function {
+    name: Option<Token>, // None for function expressions
+    pub params: Vec<Param>,
+    pub rest: Option<Token>, // collects any arguments past the other parameters into a list
+    pub body: Vec<Stmt>,
+}
+
+impl Function {
+    pub fn new(
+        name: Option<Token>,
+        params: Vec<Param>,
+        rest: Option<Token>,
+        body: Vec<Stmt>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            rest,
+            body,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_ref().map_or("<lambda>", |name| &name.lexeme)
+    }
+
+    // parameters with defaults come after all the ones without
+    pub fn min_arity(&self) -> usize {
+        self.params
+            .iter()
+            .take_while(|param| param.default.is_none())
+            .count()
+    }
+
+    pub fn max_arity(&self) -> Option<usize> {
+        match self.rest {
+            Some(_) => None,
+            None => Some(self.params.len()),
+        }
+    }
+}
+
+pub struct Param {
+    pub name: Token,
+    pub default: Option<Expr>, // evaluated on each call that leaves the argument out
+}
+
+impl Param {
+    pub fn new(name: Token, default: Option<Expr>) -> Self {
+        Self { name, default }
+    }
+}
+
+pub struct Return {
+    value: Option<Expr>,
+}
+
+impl Return {
+    pub fn new(value: Option<Expr>) -> Self {
+        Self { value }
+    }
+}
+
+pub struct Import {
+    path: String,
+    alias: Option<Token>, // the module is bound to the file's name without one
+    line: u32,
+}
+
+impl Import {
+    pub fn new(path: String, alias: Option<Token>, line: u32) -> Self {
+        Self { path, alias, line }
+    }
 }
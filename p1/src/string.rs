@@ -0,0 +1,199 @@
+use crate::{
+    callable::NativeFn,
+    error::{ErrorType, RuntimeError},
+    expr::Literal,
+    interpreter::Interpreter,
+    list, natives,
+};
+
+// strings are immutable, every method returns a new value. Positions and lengths count chars,
+// not bytes, so "héllo".len() is 5
+pub fn method(name: &str) -> Option<NativeFn> {
+    match name {
+        "len" => Some(NativeFn::new("len", 0, len)),
+        "substring" => Some(NativeFn::new("substring", 2, substring)),
+        "indexOf" => Some(NativeFn::new("indexOf", 1, index_of)),
+        "contains" => Some(NativeFn::new("contains", 1, contains)),
+        "startsWith" => Some(NativeFn::new("startsWith", 1, starts_with)),
+        "endsWith" => Some(NativeFn::new("endsWith", 1, ends_with)),
+        "split" => Some(NativeFn::new("split", 1, split)),
+        "join" => Some(NativeFn::new("join", 1, join)),
+        "trim" => Some(NativeFn::new("trim", 0, trim)),
+        "upper" => Some(NativeFn::new("upper", 0, upper)),
+        "lower" => Some(NativeFn::new("lower", 0, lower)),
+        "replace" => Some(NativeFn::new("replace", 2, replace)),
+        "chars" => Some(NativeFn::new("chars", 0, chars)),
+        "repeat" => Some(NativeFn::new("repeat", 1, repeat)),
+        _ => None,
+    }
+}
+
+// the char at `at`, for `s[i]`
+pub fn char_at(s: &str, at: &Literal) -> Result<Literal, RuntimeError> {
+    let at = natives::index("string", s.chars().count(), at)?;
+    Ok(Literal::String(s.chars().nth(at).unwrap().to_string()))
+}
+
+// the byte offset of char `at`, `at` is at most the char count
+fn byte_offset(s: &str, at: usize) -> usize {
+    s.char_indices()
+        .nth(at)
+        .map_or(s.len(), |(offset, _)| offset)
+}
+
+fn len(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::Integer(
+        natives::receiver(&args, Literal::as_str).chars().count() as i64,
+    ))
+}
+
+// start..end, end is exclusive
+fn substring(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let s = natives::receiver(&args, Literal::as_str);
+    let len = s.chars().count();
+    let start = natives::position("string", len, &args[1])?;
+    let end = natives::position("string", len, &args[2])?;
+    if start > end {
+        return Err(RuntimeError::new(
+            ErrorType::IndexError,
+            format!(
+                "Substring start {} is greater than substring end {}",
+                start, end
+            ),
+        ));
+    }
+    let (start, end) = (byte_offset(s, start), byte_offset(s, end));
+    Ok(Literal::String(s[start..end].to_owned()))
+}
+
+// -1 if it isn't there
+fn index_of(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let s = natives::receiver(&args, Literal::as_str);
+    let needle = natives::string_arg("indexOf", &args[1])?;
+    let at = s
+        .find(needle)
+        .map_or(-1, |offset| s[..offset].chars().count() as i64);
+    Ok(Literal::Integer(at))
+}
+
+fn contains(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let needle = natives::string_arg("contains", &args[1])?;
+    Ok(Literal::Boolean(
+        natives::receiver(&args, Literal::as_str).contains(needle),
+    ))
+}
+
+fn starts_with(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let prefix = natives::string_arg("startsWith", &args[1])?;
+    Ok(Literal::Boolean(
+        natives::receiver(&args, Literal::as_str).starts_with(prefix),
+    ))
+}
+
+fn ends_with(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let suffix = natives::string_arg("endsWith", &args[1])?;
+    Ok(Literal::Boolean(
+        natives::receiver(&args, Literal::as_str).ends_with(suffix),
+    ))
+}
+
+// use chars() to split into single chars, an empty separator would be ambiguous
+fn split(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let separator = natives::string_arg("split", &args[1])?;
+    if separator.is_empty() {
+        return Err(RuntimeError::new(
+            ErrorType::ValueError,
+            "split separator can't be empty".to_owned(),
+        ));
+    }
+    let parts = natives::receiver(&args, Literal::as_str)
+        .split(separator)
+        .map(|part| Literal::String(part.to_owned()))
+        .collect();
+    Ok(list::new_list(parts))
+}
+
+// the receiver goes between the items, `", ".join(xs)`. Items that aren't strings are
+// printed the way `print` would
+fn join(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let items = match args[1].as_list() {
+        Some(items) => items.borrow(),
+        None => {
+            return Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!("join expected a list but got {}", args[1].describe()),
+            ))
+        }
+    };
+    let joined = items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(natives::receiver(&args, Literal::as_str));
+    Ok(Literal::String(joined))
+}
+
+// whitespace at both ends
+fn trim(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::String(
+        natives::receiver(&args, Literal::as_str).trim().to_owned(),
+    ))
+}
+
+fn upper(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::String(
+        natives::receiver(&args, Literal::as_str).to_uppercase(),
+    ))
+}
+
+fn lower(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    Ok(Literal::String(
+        natives::receiver(&args, Literal::as_str).to_lowercase(),
+    ))
+}
+
+// every occurrence
+fn replace(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let from = natives::string_arg("replace", &args[1])?;
+    let to = natives::string_arg("replace", &args[2])?;
+    Ok(Literal::String(
+        natives::receiver(&args, Literal::as_str).replace(from, to),
+    ))
+}
+
+fn chars(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let chars = natives::receiver(&args, Literal::as_str)
+        .chars()
+        .map(|c| Literal::String(c.to_string()))
+        .collect();
+    Ok(list::new_list(chars))
+}
+
+// results longer than this are an error rather than an attempt to allocate them
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+fn repeat(_: &mut Interpreter, args: Vec<Literal>) -> Result<Literal, RuntimeError> {
+    let s = natives::receiver(&args, Literal::as_str);
+    let count = match &args[1] {
+        Literal::Integer(i) if *i >= 0 => *i as usize,
+        other => {
+            return Err(RuntimeError::new(
+                ErrorType::TypeError,
+                format!(
+                    "repeat expected a non-negative integer but got {}",
+                    other.describe()
+                ),
+            ))
+        }
+    };
+    match s.len().checked_mul(count) {
+        Some(len) if len <= MAX_REPEAT_LEN => Ok(Literal::String(s.repeat(count))),
+        _ => Err(RuntimeError::new(
+            ErrorType::ValueError,
+            format!(
+                "repeat would make a string longer than {} bytes",
+                MAX_REPEAT_LEN
+            ),
+        )),
+    }
+}
@@ -1,3 +1,5 @@
+use std::rc::Rc;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TokenType {
     // Single-character tokens.
@@ -5,6 +7,10 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Question,
     Comma,
     Dot,
     Minus,
@@ -12,6 +18,11 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     // One or two character tokens.
     Bang,
@@ -22,6 +33,19 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    TildeSlash,
+    StarStar,
+    LessLess,
+    GreaterGreater,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    PlusPlus,
+    MinusMinus,
+    Arrow,
+    DotDotDot,
 
     // Literals.
     Identifier,
@@ -30,36 +54,82 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    As,
+    Break,
+    Catch,
     Class,
+    Const,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
     Eof,
 }
 
+// single source of truth for reserved words, the scanner and any tooling look keywords up here
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("and", TokenType::And),
+    ("as", TokenType::As),
+    ("break", TokenType::Break),
+    ("catch", TokenType::Catch),
+    ("class", TokenType::Class),
+    ("const", TokenType::Const),
+    ("continue", TokenType::Continue),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("finally", TokenType::Finally),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("import", TokenType::Import),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("throw", TokenType::Throw),
+    ("true", TokenType::True),
+    ("try", TokenType::Try),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
+
+pub fn keyword(text: &str) -> Option<TokenType> {
+    KEYWORDS
+        .iter()
+        .find(|(word, _)| *word == text)
+        .map(|(_, token_type)| token_type.clone())
+}
+
 #[derive(Debug, Clone)]
 pub enum Literal {
+    Integer(i64),
     Number(f64),
-    String(String),
-    Identifier(String),
+    String(Rc<str>),
+    Identifier(Rc<str>),
 }
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: Rc<str>,
     pub literal: Option<Literal>,
     pub line: u32,
 }
@@ -67,7 +137,7 @@ pub struct Token {
 impl Token {
     pub fn new(
         token_type: TokenType,
-        lexeme: String,
+        lexeme: Rc<str>,
         literal: Option<Literal>,
         line: u32,
     ) -> Token {
@@ -0,0 +1,92 @@
+use std::{path::Path, process::Command};
+
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+// runs a script from tests/scripts with the interpreter binary
+fn run(script: &str) -> Run {
+    run_with_flags(&[], script)
+}
+
+fn run_with_flags(flags: &[&str], script: &str) -> Run {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scripts")
+        .join(script);
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox_intrp"))
+        .args(flags)
+        .arg(path)
+        .output()
+        .expect("couldn't run the interpreter");
+    Run {
+        code: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+#[test]
+fn nested_blocks_in_recursion_overflow_cleanly() {
+    let run = run("nested_blocks_recursion.lox");
+    assert!(run.stderr.contains("StackOverflowError"), "{}", run.stderr);
+    assert_eq!(run.code, Some(70));
+}
+
+#[test]
+fn importing_the_entry_script_is_a_cycle() {
+    let run = run("imports_itself.lox");
+    assert_eq!(run.stdout, "running\n");
+    assert!(run.stderr.contains("Import cycle"), "{}", run.stderr);
+}
+
+#[test]
+fn string_methods_raise_typed_errors() {
+    let run = run("string_method_errors.lox");
+    assert_eq!(
+        run.stdout,
+        "TypeError\nIndexError\nIndexError\nValueError\nValueError\n"
+    );
+}
+
+#[test]
+fn exit_rejects_codes_outside_a_byte() {
+    let run = run("exit_out_of_range.lox");
+    assert_eq!(
+        run.stdout,
+        "ValueError: Exit code 256 must be between 0 and 255\n\
+         ValueError: Exit code -1 must be between 0 and 255\n"
+    );
+    assert_eq!(run.code, Some(3));
+}
+
+#[test]
+fn modules_dont_see_the_importers_variables() {
+    let run = run("module_scope.lox");
+    assert_eq!(run.stdout, "NameError\nNameError\nmain's\n");
+}
+
+#[test]
+fn shadowing_a_native_doesnt_reach_modules() {
+    let run = run("shadows_len.lox");
+    assert_eq!(run.stdout, "3\n0\n");
+}
+
+#[test]
+fn strict_conditions_report_their_line() {
+    let run = run_with_flags(&["--strict-truthiness"], "strict_conditions.lox");
+    assert!(run.stderr.contains("[Line 2] TypeError"), "{}", run.stderr);
+    assert!(run.stderr.contains("[Line 5] TypeError"), "{}", run.stderr);
+}
+
+#[test]
+fn bad_map_literal_keys_report_their_line() {
+    let run = run("map_key_error.lox");
+    assert!(
+        run.stderr
+            .contains("[Line 2] TypeError: [1] can't be used as a map key"),
+        "{}",
+        run.stderr
+    );
+}